@@ -1,17 +1,31 @@
+use avian3d::prelude::*;
 use bevy::{
     audio::{PlaybackMode, Volume},
     prelude::*,
 };
 use bevy_fps_controller::controller::*;
 
-use crate::core::*;
+use crate::{core::*, player::detect_ground_surface};
+
+/// Distance travelled on the ground between two footsteps.
+const FOOTSTEP_DISTANCE: f32 = 4.;
+
+/// Horizontal speed below which the player is considered to be standing still.
+const FOOTSTEP_MIN_SPEED: f32 = 1.;
 
 pub struct AudioPlugin;
 
 impl Plugin for AudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
-            .add_systems(Update, (dive_sound, land_sound, shatter_sound))
+            .add_systems(
+                Update,
+                (dive_sound, shatter_sound, checkpoint_sound, boost_sound),
+            )
+            .add_systems(
+                Update,
+                (land_sound, footstep_sound.in_set(GameplaySet)).after(detect_ground_surface),
+            )
             .add_systems(OnEnter(AppState::InGame), ocean_sound)
             .add_systems(OnExit(AppState::InGame), cleanup::<OceanSound>);
     }
@@ -58,9 +72,9 @@ fn ocean_sound(mut cmd: Commands, sounds: Res<Sounds>) {
 }
 
 fn dive_sound(
-    mut cmd: Commands,
     q: Query<&Transform, With<LogicalPlayer>>,
     mut er: EventReader<Respawn<LogicalPlayer>>,
+    mut ew: EventWriter<PlaySound>,
     sounds: Res<Sounds>,
 ) {
     for e in er.read() {
@@ -69,44 +83,99 @@ fn dive_sound(
                 continue;
             };
 
-            cmd.spawn((
-                AudioPlayer::new(sounds.dive_sound.clone()),
-                PlaybackSettings {
-                    mode: PlaybackMode::Despawn,
-                    volume: Volume::Linear(0.15),
-                    ..default()
-                },
-            ));
+            ew.write(PlaySound::new(sounds.dive_sound.clone(), 0.15));
         }
     }
 }
 
-fn shatter_sound(mut cmd: Commands, sounds: Res<Sounds>, mut er: EventReader<SpawnLevel>) {
+fn shatter_sound(
+    sounds: Res<Sounds>,
+    mut er: EventReader<SpawnLevel>,
+    mut ew: EventWriter<PlaySound>,
+) {
+    for _ in er.read() {
+        ew.write(PlaySound::new(sounds.shatter_sound.clone(), 0.2));
+    }
+}
+
+fn checkpoint_sound(
+    sounds: Res<Sounds>,
+    mut er: EventReader<CheckpointReached>,
+    mut ew: EventWriter<PlaySound>,
+) {
     for _ in er.read() {
-        cmd.spawn((
-            AudioPlayer::new(sounds.shatter_sound.clone()),
-            PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                volume: Volume::Linear(0.2),
-                ..default()
-            },
-        ));
+        ew.write(PlaySound::new(sounds.glass_sound.clone(), 0.1));
     }
 }
 
-fn land_sound(mut cmd: Commands, q: Query<&FpsController>, sounds: Res<Sounds>) {
-    for controller in &q {
+fn boost_sound(sounds: Res<Sounds>, mut er: EventReader<Boosted>, mut ew: EventWriter<PlaySound>) {
+    for _ in er.read() {
+        ew.write(PlaySound::new(sounds.boost_sound.clone(), 0.2));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SurfaceCue {
+    Footstep,
+    Landing,
+}
+
+impl Sounds {
+    pub fn surface_sound(&self, surface: SurfaceMaterial, cue: SurfaceCue) -> PlaySound {
+        let (sound, volume, speed) = match (surface, cue) {
+            (SurfaceMaterial::Stone, SurfaceCue::Landing) => (&self.land_sound, 0.2, 1.0),
+            (SurfaceMaterial::Stone, SurfaceCue::Footstep) => (&self.land_sound, 0.08, 1.3),
+            (SurfaceMaterial::Metal, SurfaceCue::Landing) => (&self.land_sound, 0.25, 1.5),
+            (SurfaceMaterial::Metal, SurfaceCue::Footstep) => (&self.land_sound, 0.1, 1.8),
+            (SurfaceMaterial::Glass, SurfaceCue::Landing) => (&self.glass_sound, 0.06, 1.4),
+            (SurfaceMaterial::Glass, SurfaceCue::Footstep) => (&self.glass_sound, 0.03, 1.8),
+            (SurfaceMaterial::Wood, SurfaceCue::Landing) => (&self.land_sound, 0.2, 0.8),
+            (SurfaceMaterial::Wood, SurfaceCue::Footstep) => (&self.land_sound, 0.08, 1.0),
+            (SurfaceMaterial::Grass, SurfaceCue::Landing) => (&self.land_sound, 0.12, 0.6),
+            (SurfaceMaterial::Grass, SurfaceCue::Footstep) => (&self.land_sound, 0.05, 0.7),
+        };
+
+        PlaySound::new(sound.clone(), volume).with_speed(speed)
+    }
+}
+
+fn land_sound(
+    q: Query<(&FpsController, &GroundSurface)>,
+    mut ew: EventWriter<PlaySound>,
+    sounds: Res<Sounds>,
+) {
+    for (controller, ground) in &q {
         if controller.ground_tick != 1 {
             continue;
         };
 
-        cmd.spawn((
-            AudioPlayer::new(sounds.land_sound.clone()),
-            PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                volume: Volume::Linear(0.2),
-                ..default()
-            },
-        ));
+        ew.write(sounds.surface_sound(ground.0.unwrap_or_default(), SurfaceCue::Landing));
+    }
+}
+
+fn footstep_sound(
+    mut q: Query<(&FpsController, &GroundSurface, &LinearVelocity, &mut Stride)>,
+    mut ew: EventWriter<PlaySound>,
+    sounds: Res<Sounds>,
+    time: Res<Time>,
+) {
+    for (controller, ground, velocity, mut stride) in &mut q {
+        let speed = velocity.0.xz().length();
+
+        // Landing already plays its own cue, footsteps start on the tick after.
+        if controller.ground_tick <= 1 || speed < FOOTSTEP_MIN_SPEED {
+            stride.0 = 0.;
+            continue;
+        }
+
+        stride.0 += speed * time.delta_secs();
+
+        if stride.0 < FOOTSTEP_DISTANCE {
+            continue;
+        }
+
+        stride.0 = 0.;
+
+        ew.write(sounds.surface_sound(ground.0.unwrap_or_default(), SurfaceCue::Footstep));
     }
 }
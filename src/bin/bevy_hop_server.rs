@@ -0,0 +1,101 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    prelude::*,
+    render::{
+        RenderPlugin,
+        settings::{RenderCreation, WgpuSettings},
+    },
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_console::clap::{self, Parser};
+use bevy_hop::prelude::*;
+
+/// Rate of the server loop, at least the highest tick rate so no fixed tick is late.
+const TICK_RATE: f64 = 128.;
+
+/// Headless Bevy Hop server.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// RON file with the server settings, see `ServerConfig`.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// UDP port to listen on.
+    #[arg(long)]
+    port: Option<u16>,
+    /// Levels to rotate through, e.g. `1,2,3`.
+    #[arg(long, value_delimiter = ',')]
+    maps: Option<Vec<usize>>,
+    #[arg(long)]
+    max_players: Option<usize>,
+    #[arg(long, value_enum)]
+    tickrate: Option<TickRate>,
+}
+
+impl Args {
+    fn config(self) -> Result<ServerConfig, String> {
+        let mut config = match &self.config {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {e}", path.display()))
+                .and_then(|x| {
+                    ron::from_str::<ServerConfig>(&x)
+                        .map_err(|e| format!("Could not parse {}: {e}", path.display()))
+                })?,
+            None => ServerConfig::default(),
+        };
+
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(maps) = self.maps {
+            config.maps = maps;
+        }
+        if let Some(max_players) = self.max_players {
+            config.max_players = max_players;
+        }
+        if let Some(tick_rate) = self.tickrate {
+            config.tick_rate = tick_rate;
+        }
+
+        Ok(config)
+    }
+}
+
+fn main() -> AppExit {
+    let config = match Args::parse().config() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{e}");
+            return AppExit::error();
+        }
+    };
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                .disable::<WinitPlugin>()
+                .disable::<bevy::audio::AudioPlugin>(),
+            ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1. / TICK_RATE)),
+            CorePlugin,
+            StatePlugin,
+            WorldPlugin,
+            DurationPlugin,
+            DedicatedServerPlugin(config),
+        ))
+        .run()
+}
@@ -0,0 +1,228 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::prelude::*;
+
+/// Older messages are dropped.
+const CHAT_HISTORY: usize = 64;
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatLog>()
+            .init_resource::<ChatInput>()
+            .add_event::<SendChat>()
+            .add_console_command::<MessageModeCommand, _>(messagemode)
+            .add_console_command::<SayCommand, _>(say)
+            .add_systems(Startup, (default_binds, default_aliases))
+            .add_systems(Update, (type_chat, send_chat).chain())
+            .add_systems(OnEnter(ChatState::Open), hold_input)
+            .add_systems(OnExit(ChatState::Open), release_input)
+            .add_systems(OnExit(AppState::InGame), close_chat);
+    }
+}
+
+/// A line typed into the chat or passed to `say`, lines starting with `/` run as console commands.
+#[derive(Event)]
+pub struct SendChat(pub String);
+
+pub struct ChatLine {
+    /// `None` for messages of the server.
+    pub name: Option<String>,
+    pub text: String,
+    pub received: Duration,
+}
+
+impl std::fmt::Display for ChatLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name}: {}", self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ChatLog(VecDeque<ChatLine>);
+
+impl ChatLog {
+    pub fn push(&mut self, name: Option<String>, text: impl Into<String>, now: Duration) {
+        let line = ChatLine {
+            name,
+            text: text.into(),
+            received: now,
+        };
+
+        info!("{line}");
+
+        if self.0.len() >= CHAT_HISTORY {
+            self.0.pop_front();
+        }
+        self.0.push_back(line);
+    }
+
+    /// Oldest first.
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &ChatLine> {
+        self.0.iter()
+    }
+}
+
+/// The message being typed while the chat is open.
+#[derive(Resource, Default)]
+pub struct ChatInput {
+    pub text: String,
+    /// Whether the player could move before the chat opened.
+    restore_input: bool,
+}
+
+fn default_binds(mut binds: ResMut<Binds>) {
+    binds.insert_default("y", "messagemode");
+}
+
+fn default_aliases(mut aliases: ResMut<Aliases>) {
+    aliases.insert_default("r", "restart");
+    aliases.insert_default("spec", "spectate");
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "messagemode")]
+struct MessageModeCommand {}
+
+fn messagemode(
+    mut log: ConsoleCommand<MessageModeCommand>,
+    app_state: Res<State<AppState>>,
+    mut ns: ResMut<NextState<ChatState>>,
+) {
+    let Some(Ok(MessageModeCommand {})) = log.take() else {
+        return;
+    };
+
+    if *app_state.get() != AppState::InGame {
+        reply!(log, "The chat is only available in game.");
+        return;
+    }
+
+    ns.set(ChatState::Open);
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "say")]
+struct SayCommand {
+    /// Message sent to everyone on the server
+    #[arg(num_args = 1.., trailing_var_arg = true, allow_hyphen_values = true)]
+    text: Vec<String>,
+}
+
+fn say(mut log: ConsoleCommand<SayCommand>, mut ew: EventWriter<SendChat>) {
+    let Some(Ok(SayCommand { text })) = log.take() else {
+        return;
+    };
+
+    ew.write(SendChat(text.join(" ")));
+}
+
+fn type_chat(
+    mut er: EventReader<KeyboardInput>,
+    state: Res<State<ChatState>>,
+    mut ns: ResMut<NextState<ChatState>>,
+    mut input: ResMut<ChatInput>,
+    console_open: Res<ConsoleOpen>,
+    mut ew: EventWriter<SendChat>,
+) {
+    // Keys pressed while the chat is closed, like the one opening it, aren't typed.
+    if *state.get() == ChatState::Closed || console_open.open {
+        er.clear();
+        return;
+    }
+
+    for e in er.read().filter(|x| x.state.is_pressed()) {
+        match &e.logical_key {
+            Key::Enter => {
+                ew.write(SendChat(std::mem::take(&mut input.text)));
+                ns.set(ChatState::Closed);
+                return;
+            }
+            Key::Escape => {
+                input.text.clear();
+                ns.set(ChatState::Closed);
+                return;
+            }
+            Key::Backspace => {
+                input.text.pop();
+            }
+            Key::Space => input.text.push(' '),
+            Key::Character(x) => input.text.extend(x.chars().filter(|x| !x.is_control())),
+            _ => {}
+        }
+
+        if input.text.chars().count() > MAX_CHAT_LENGTH {
+            input.text = input.text.chars().take(MAX_CHAT_LENGTH).collect();
+        }
+    }
+}
+
+fn send_chat(
+    mut er: EventReader<SendChat>,
+    client: Option<Res<Client>>,
+    mut buffer: ResMut<CommandBuffer>,
+    mut chat_log: ResMut<ChatLog>,
+    time: Res<Time<Real>>,
+) {
+    for SendChat(text) in er.read() {
+        let text = sanitize_chat(text);
+
+        if text.is_empty() {
+            continue;
+        }
+
+        // Chat commands go through the console, `/r` runs the `r` alias.
+        if let Some(command) = text.strip_prefix('/') {
+            buffer.push(command);
+            continue;
+        }
+
+        match client.as_ref().filter(|x| x.id().is_some()) {
+            Some(client) => client.say(&text),
+            None => chat_log.push(Some(String::from("You")), text, time.elapsed()),
+        }
+    }
+}
+
+/// Stops the player like the pause menu does, keys go to the chat instead.
+fn hold_input(
+    mut input: ResMut<ChatInput>,
+    mut q_player: Query<(&mut FpsController, &mut FpsControllerInput), With<LogicalPlayer>>,
+) {
+    for (mut controller, mut controller_input) in &mut q_player {
+        input.restore_input = controller.enable_input;
+        controller.enable_input = false;
+
+        controller_input.movement = Vec3::ZERO;
+        controller_input.jump = false;
+        controller_input.sprint = false;
+        controller_input.crouch = false;
+    }
+}
+
+fn release_input(
+    mut input: ResMut<ChatInput>,
+    mut q_controller: Query<&mut FpsController, With<LogicalPlayer>>,
+) {
+    input.text.clear();
+
+    for mut controller in &mut q_controller {
+        controller.enable_input = input.restore_input;
+    }
+}
+
+fn close_chat(mut ns: ResMut<NextState<ChatState>>) {
+    ns.set(ChatState::Closed);
+}
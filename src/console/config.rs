@@ -0,0 +1,671 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_console::*;
+use clap::Parser;
+
+use crate::state::*;
+
+use super::CVars;
+
+/// Directory console config files are read from and written to.
+pub const CONFIG_DIR: &str = "cfg";
+
+/// Archived cvars, binds and aliases are written to this file whenever they change.
+const CONFIG_FILE: &str = "config.cfg";
+
+/// Executed once loading is done, for the player's own binds, aliases and cvars.
+const AUTOEXEC_FILE: &str = "autoexec.cfg";
+
+/// Upper bound of buffered commands executed per frame, long configs run over several frames.
+const MAX_COMMANDS_PER_FRAME: usize = 256;
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandBuffer>()
+            .init_resource::<Aliases>()
+            .init_resource::<Binds>()
+            .add_console_command::<ExecCommand, _>(exec)
+            .add_console_command::<BindCommand, _>(bind)
+            .add_console_command::<UnbindCommand, _>(unbind)
+            .add_console_command::<AliasCommand, _>(alias)
+            .add_systems(Startup, exec_config)
+            .add_systems(OnExit(AppState::Loading), exec_autoexec)
+            .add_systems(
+                Update,
+                (
+                    trigger_binds
+                        .run_if(in_state(AppState::InGame).and(in_state(ChatState::Closed))),
+                    execute_command_buffer,
+                )
+                    .chain()
+                    .before(ConsoleSet::Commands),
+            )
+            .add_systems(Last, save_config);
+    }
+}
+
+/// Console lines waiting to be executed, filled by `exec`, binds and aliases.
+#[derive(Resource, Default)]
+pub struct CommandBuffer(VecDeque<BufferedLine>);
+
+struct BufferedLine {
+    line: String,
+    /// The aliases expanded to get to this line, an alias expanding to itself is recursive.
+    aliases: Vec<String>,
+}
+
+impl CommandBuffer {
+    pub fn push(&mut self, line: impl Into<String>) {
+        self.0.push_back(BufferedLine {
+            line: line.into(),
+            aliases: Vec::new(),
+        });
+    }
+
+    /// Queues every line of a config file in the config directory.
+    pub fn exec(&mut self, file: &str) -> std::io::Result<()> {
+        let config = read_config(file)?;
+        config.lines().for_each(|x| self.push(x));
+        Ok(())
+    }
+
+    /// Pops the next command with its arguments, expanding aliases on the way.
+    /// A recursive alias is dropped with everything it expanded to, and returned as the error.
+    fn next_command(&mut self, aliases: &Aliases) -> Option<Result<Vec<String>, String>> {
+        while let Some(BufferedLine {
+            line,
+            aliases: expanded,
+        }) = self.0.pop_front()
+        {
+            let commands = split_commands(&line);
+
+            // Lines with several commands are queued up front again, one command each.
+            if commands.len() > 1 {
+                for command in commands.into_iter().rev() {
+                    self.0.push_front(BufferedLine {
+                        line: command.to_string(),
+                        aliases: expanded.clone(),
+                    });
+                }
+                continue;
+            }
+
+            let args = commands.first().map(|x| tokenize(x)).unwrap_or_default();
+
+            let Some(command_name) = args.first() else {
+                continue;
+            };
+
+            let Some(expansion) = aliases.0.get(command_name) else {
+                return Some(Ok(args));
+            };
+
+            if expanded.contains(command_name) {
+                let name = command_name.clone();
+                self.0.retain(|x| !x.aliases.contains(&name));
+                return Some(Err(name));
+            }
+
+            let mut expanded = expanded;
+            expanded.push(command_name.clone());
+
+            self.0.push_front(BufferedLine {
+                line: expansion.clone(),
+                aliases: expanded,
+            });
+        }
+
+        None
+    }
+}
+
+/// Console lines run in place of a name, expanded by the command buffer.
+#[derive(Resource, Default)]
+pub struct Aliases(BTreeMap<String, String>);
+
+impl Aliases {
+    /// Defines an alias at startup, before the config is executed so players can redefine it.
+    pub fn insert_default(&mut self, name: &str, command: &str) {
+        self.0.insert(name.to_string(), command.to_string());
+    }
+
+    /// The `alias` lines recreating every alias.
+    fn archive(&self) -> impl Iterator<Item = String> {
+        self.0
+            .iter()
+            .map(|(name, command)| format!("alias \"{name}\" \"{command}\"\n"))
+    }
+}
+
+/// Console lines bound to keys, keyed by the key name as typed.
+#[derive(Resource, Default)]
+pub struct Binds {
+    binds: BTreeMap<String, String>,
+    /// Keys bound by default, an unbound default has to be archived as `unbind`.
+    defaults: BTreeSet<String>,
+}
+
+impl Binds {
+    /// Binds a key at startup, before the config is executed so players can rebind it.
+    pub fn insert_default(&mut self, key: &str, command: &str) {
+        self.binds.insert(key.to_string(), command.to_string());
+        self.defaults.insert(key.to_string());
+    }
+
+    /// The `unbind` and `bind` lines recreating the binds on top of the defaults.
+    fn archive(&self) -> impl Iterator<Item = String> {
+        self.defaults
+            .iter()
+            .filter(|x| !self.binds.contains_key(*x))
+            .map(|key| format!("unbind \"{key}\"\n"))
+            .chain(
+                self.binds
+                    .iter()
+                    .map(|(key, command)| format!("bind \"{key}\" \"{command}\"\n")),
+            )
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_config(file: &str) -> std::io::Result<String> {
+    std::fs::read_to_string(config_path(file))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_config(_file: &str) -> std::io::Result<String> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "config files are not supported on the web",
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn config_path(file: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(CONFIG_DIR).join(file);
+
+    match path.extension() {
+        Some(_) => path,
+        None => path.with_extension("cfg"),
+    }
+}
+
+fn exec_config(mut buffer: ResMut<CommandBuffer>) {
+    // A missing config just means nothing was archived yet.
+    let _ = buffer.exec(CONFIG_FILE);
+}
+
+fn exec_autoexec(mut buffer: ResMut<CommandBuffer>) {
+    let _ = buffer.exec(AUTOEXEC_FILE);
+
+    // Like Source, `+tickrate 100 +connect host` on the command line runs after the autoexec.
+    for command in launch_commands(std::env::args().skip(1)) {
+        buffer.push(command);
+    }
+}
+
+/// Groups the arguments into commands, each starting with a `+` argument.
+fn launch_commands(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut commands = Vec::<String>::new();
+
+    for arg in args {
+        if let Some(command) = arg.strip_prefix('+') {
+            commands.push(command.to_string());
+            continue;
+        }
+
+        // Arguments before the first command aren't meant for the console.
+        let Some(command) = commands.last_mut() else {
+            continue;
+        };
+
+        match arg.contains(char::is_whitespace) {
+            true => command.push_str(&format!(" \"{arg}\"")),
+            false => command.push_str(&format!(" {arg}")),
+        }
+    }
+
+    commands
+}
+
+/// Splits a console line into its `;` separated commands, ignoring separators inside quotes.
+fn split_commands(line: &str) -> Vec<&str> {
+    let line = line.split("//").next().unwrap_or_default();
+
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                commands.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    commands.push(&line[start..]);
+
+    commands
+        .into_iter()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// Splits a command into whitespace separated tokens, keeping quoted tokens together.
+fn tokenize(command: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn execute_command_buffer(
+    mut buffer: ResMut<CommandBuffer>,
+    aliases: Res<Aliases>,
+    config: Res<ConsoleConfiguration>,
+    mut ew: EventWriter<ConsoleCommandEntered>,
+    mut ew_print: EventWriter<PrintConsoleLine>,
+) {
+    if buffer.0.is_empty() {
+        return;
+    }
+
+    for _ in 0..MAX_COMMANDS_PER_FRAME {
+        let mut args = match buffer.next_command(&aliases) {
+            Some(Ok(args)) => args,
+            Some(Err(alias)) => {
+                ew_print.write(PrintConsoleLine::new(
+                    format!("Alias {alias} expands to itself, skipped.").into(),
+                ));
+                continue;
+            }
+            None => return,
+        };
+
+        let command_name = args.remove(0);
+
+        if !config.commands.contains_key(command_name.as_str()) {
+            ew_print.write(PrintConsoleLine::new(
+                format!("Unknown command: {command_name}").into(),
+            ));
+            continue;
+        }
+
+        ew.write(ConsoleCommandEntered { command_name, args });
+    }
+
+    warn!("Command buffer limit reached, the remaining commands run next frame.");
+}
+
+fn trigger_binds(
+    keys: Res<ButtonInput<KeyCode>>,
+    binds: Res<Binds>,
+    console_open: Res<ConsoleOpen>,
+    mut buffer: ResMut<CommandBuffer>,
+) {
+    if console_open.open {
+        return;
+    }
+
+    for (key, command) in &binds.binds {
+        if parse_key(key).is_some_and(|x| keys.just_pressed(x)) {
+            buffer.push(command.clone());
+        }
+    }
+}
+
+fn save_config(cvars: Res<CVars>, binds: Res<Binds>, aliases: Res<Aliases>) {
+    if !cvars.is_changed() && !binds.is_changed() && !aliases.is_changed() {
+        return;
+    }
+
+    let config = binds
+        .archive()
+        .chain(aliases.archive())
+        .chain(
+            cvars
+                .iter()
+                .filter(|x| x.flags.archive)
+                .map(|x| format!("{} \"{}\"\n", x.name, x.value)),
+        )
+        .collect::<String>();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = std::fs::create_dir_all(CONFIG_DIR)
+        .and_then(|_| std::fs::write(config_path(CONFIG_FILE), config))
+    {
+        warn!("Could not write {CONFIG_FILE}: {e}");
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = config;
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "exec")]
+struct ExecCommand {
+    /// Config file in the cfg directory
+    #[arg(index = 1)]
+    file: String,
+}
+
+fn exec(mut log: ConsoleCommand<ExecCommand>, mut buffer: ResMut<CommandBuffer>) {
+    let Some(Ok(ExecCommand { file })) = log.take() else {
+        return;
+    };
+
+    match buffer.exec(&file) {
+        Ok(()) => reply!(log, "Executing {file}"),
+        Err(e) => reply!(log, "Couldn't exec {file}: {e}"),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "bind")]
+struct BindCommand {
+    #[arg(index = 1)]
+    key: String,
+    /// Console line to run when the key is pressed
+    #[arg(index = 2, num_args = 0.., trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+fn bind(mut log: ConsoleCommand<BindCommand>, mut binds: ResMut<Binds>) {
+    let Some(Ok(BindCommand { key, command })) = log.take() else {
+        return;
+    };
+
+    let key = key.to_lowercase();
+
+    if parse_key(&key).is_none() {
+        reply!(log, "\"{key}\" isn't a valid key");
+        return;
+    }
+
+    if command.is_empty() {
+        match binds.binds.get(&key) {
+            Some(command) => reply!(log, "\"{key}\" = \"{command}\""),
+            None => reply!(log, "\"{key}\" is not bound"),
+        }
+        return;
+    }
+
+    binds.binds.insert(key, command.join(" "));
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "unbind")]
+struct UnbindCommand {
+    #[arg(index = 1)]
+    key: String,
+}
+
+fn unbind(mut log: ConsoleCommand<UnbindCommand>, mut binds: ResMut<Binds>) {
+    let Some(Ok(UnbindCommand { key })) = log.take() else {
+        return;
+    };
+
+    if binds.binds.remove(&key.to_lowercase()).is_none() {
+        reply!(log, "\"{key}\" is not bound");
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "alias")]
+struct AliasCommand {
+    #[arg(index = 1)]
+    name: Option<String>,
+    /// Console line the alias expands to
+    #[arg(index = 2, num_args = 0.., trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+fn alias(
+    mut log: ConsoleCommand<AliasCommand>,
+    mut aliases: ResMut<Aliases>,
+    config: Res<ConsoleConfiguration>,
+) {
+    let Some(Ok(AliasCommand { name, command })) = log.take() else {
+        return;
+    };
+
+    let Some(name) = name else {
+        for (name, command) in &aliases.0 {
+            reply!(log, "{name} : {command}");
+        }
+        return;
+    };
+
+    if command.is_empty() {
+        match aliases.0.get(&name) {
+            Some(command) => reply!(log, "{name} : {command}"),
+            None => reply!(log, "Alias {name} not found"),
+        }
+        return;
+    }
+
+    if config.commands.contains_key(name.as_str()) {
+        reply!(log, "Can't alias {name}, it is already a command");
+        return;
+    }
+
+    aliases.0.insert(name, command.join(" "));
+}
+
+/// Parses Source style key names as used by `bind`.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let key = match name.to_lowercase().as_str() {
+        "a" => KeyCode::KeyA,
+        "b" => KeyCode::KeyB,
+        "c" => KeyCode::KeyC,
+        "d" => KeyCode::KeyD,
+        "e" => KeyCode::KeyE,
+        "f" => KeyCode::KeyF,
+        "g" => KeyCode::KeyG,
+        "h" => KeyCode::KeyH,
+        "i" => KeyCode::KeyI,
+        "j" => KeyCode::KeyJ,
+        "k" => KeyCode::KeyK,
+        "l" => KeyCode::KeyL,
+        "m" => KeyCode::KeyM,
+        "n" => KeyCode::KeyN,
+        "o" => KeyCode::KeyO,
+        "p" => KeyCode::KeyP,
+        "q" => KeyCode::KeyQ,
+        "r" => KeyCode::KeyR,
+        "s" => KeyCode::KeyS,
+        "t" => KeyCode::KeyT,
+        "u" => KeyCode::KeyU,
+        "v" => KeyCode::KeyV,
+        "w" => KeyCode::KeyW,
+        "x" => KeyCode::KeyX,
+        "y" => KeyCode::KeyY,
+        "z" => KeyCode::KeyZ,
+        "0" => KeyCode::Digit0,
+        "1" => KeyCode::Digit1,
+        "2" => KeyCode::Digit2,
+        "3" => KeyCode::Digit3,
+        "4" => KeyCode::Digit4,
+        "5" => KeyCode::Digit5,
+        "6" => KeyCode::Digit6,
+        "7" => KeyCode::Digit7,
+        "8" => KeyCode::Digit8,
+        "9" => KeyCode::Digit9,
+        "f1" => KeyCode::F1,
+        "f2" => KeyCode::F2,
+        "f3" => KeyCode::F3,
+        "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5,
+        "f6" => KeyCode::F6,
+        "f7" => KeyCode::F7,
+        "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9,
+        "f10" => KeyCode::F10,
+        "f11" => KeyCode::F11,
+        "f12" => KeyCode::F12,
+        "space" => KeyCode::Space,
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "backspace" => KeyCode::Backspace,
+        "shift" => KeyCode::ShiftLeft,
+        "rshift" => KeyCode::ShiftRight,
+        "ctrl" => KeyCode::ControlLeft,
+        "rctrl" => KeyCode::ControlRight,
+        "alt" => KeyCode::AltLeft,
+        "ralt" => KeyCode::AltRight,
+        "capslock" => KeyCode::CapsLock,
+        "uparrow" => KeyCode::ArrowUp,
+        "downarrow" => KeyCode::ArrowDown,
+        "leftarrow" => KeyCode::ArrowLeft,
+        "rightarrow" => KeyCode::ArrowRight,
+        "ins" => KeyCode::Insert,
+        "del" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pgup" => KeyCode::PageUp,
+        "pgdn" => KeyCode::PageDown,
+        "-" => KeyCode::Minus,
+        "=" => KeyCode::Equal,
+        "[" => KeyCode::BracketLeft,
+        "]" => KeyCode::BracketRight,
+        "\\" => KeyCode::Backslash,
+        ";" => KeyCode::Semicolon,
+        "'" => KeyCode::Quote,
+        "," => KeyCode::Comma,
+        "." => KeyCode::Period,
+        "/" => KeyCode::Slash,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(aliases: &[(&str, &str)]) -> Aliases {
+        Aliases(
+            aliases
+                .iter()
+                .map(|(name, command)| (name.to_string(), command.to_string()))
+                .collect(),
+        )
+    }
+
+    fn commands(buffer: &mut CommandBuffer, aliases: &Aliases) -> Vec<Result<Vec<String>, String>> {
+        std::iter::from_fn(|| buffer.next_command(aliases)).collect()
+    }
+
+    #[test]
+    fn split_commands_ignores_quoted_separators_and_comments() {
+        assert_eq!(
+            split_commands("bind q \"kill; say hi\"; echo done // kill;"),
+            vec!["bind q \"kill; say hi\"", "echo done"]
+        );
+        assert!(split_commands(" ; // comment").is_empty());
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_tokens_together() {
+        assert_eq!(
+            tokenize("  say \"hello  world\" again"),
+            vec!["say", "hello  world", "again"]
+        );
+        assert_eq!(tokenize("bind q \"\""), vec!["bind", "q"]);
+    }
+
+    #[test]
+    fn launch_commands_start_at_plus_arguments() {
+        let args = ["--fullscreen", "+tickrate", "100", "+connect", "my host"];
+
+        assert_eq!(
+            launch_commands(args.into_iter().map(String::from)),
+            vec!["tickrate 100", "connect \"my host\""]
+        );
+    }
+
+    #[test]
+    fn aliases_expand_in_place() {
+        let aliases = aliases(&[("jumpthrow", "+jump; -attack"), ("both", "jumpthrow; kill")]);
+        let mut buffer = CommandBuffer::default();
+        buffer.push("both; echo done");
+
+        let args = |x: &[&str]| Ok::<_, String>(x.iter().map(|x| x.to_string()).collect());
+
+        assert_eq!(
+            commands(&mut buffer, &aliases),
+            vec![
+                args(&["+jump"]),
+                args(&["-attack"]),
+                args(&["kill"]),
+                args(&["echo", "done"])
+            ]
+        );
+    }
+
+    #[test]
+    fn archive_unbound_defaults_and_aliases() {
+        let mut binds = Binds::default();
+        binds.insert_default("y", "messagemode");
+        binds.insert_default("t", "say hi");
+        binds.binds.remove("y");
+        binds.binds.insert("f".to_string(), "savepos".to_string());
+
+        assert_eq!(
+            binds.archive().collect::<Vec<_>>(),
+            vec![
+                "unbind \"y\"\n",
+                "bind \"f\" \"savepos\"\n",
+                "bind \"t\" \"say hi\"\n"
+            ]
+        );
+
+        let aliases = aliases(&[("reset", "level 1; pause")]);
+        let line = aliases.archive().next().unwrap();
+
+        assert_eq!(split_commands(&line), vec![line.trim()]);
+        assert_eq!(tokenize(&line), vec!["alias", "reset", "level 1; pause"]);
+    }
+
+    #[test]
+    fn recursive_aliases_are_dropped_once() {
+        let aliases = aliases(&[("a", "b; a; a"), ("b", "echo b")]);
+        let mut buffer = CommandBuffer::default();
+        buffer.push("a");
+        buffer.push("echo after");
+
+        assert_eq!(
+            commands(&mut buffer, &aliases),
+            vec![
+                Ok(vec!["echo".to_string(), "b".to_string()]),
+                Err("a".to_string()),
+                Ok(vec!["echo".to_string(), "after".to_string()])
+            ]
+        );
+    }
+}
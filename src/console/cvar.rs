@@ -0,0 +1,401 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use bevy::prelude::*;
+use bevy_console::*;
+use clap::{Arg, Command, Parser};
+use thiserror::Error;
+
+pub const SV_CHEATS: &str = "sv_cheats";
+
+pub struct CVarPlugin;
+
+impl Plugin for CVarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CVars>()
+            .add_cvar(
+                CVar::bool(SV_CHEATS, false, "Allow changing cvars flagged as cheats.").notify(),
+            )
+            .add_console_command::<CVarListCommand, _>(cvarlist)
+            .add_systems(Startup, register_cvar_commands)
+            .add_systems(Update, cvar_commands.in_set(ConsoleSet::Commands));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CVarValue {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+}
+
+impl CVarValue {
+    /// Parses `raw` as a value of the same type as `self`.
+    fn parse(&self, raw: &str) -> Option<CVarValue> {
+        match self {
+            CVarValue::Float(_) => raw
+                .parse()
+                .ok()
+                .filter(|x: &f32| x.is_finite())
+                .map(CVarValue::Float),
+            CVarValue::Int(_) => raw.parse().ok().map(CVarValue::Int),
+            CVarValue::Bool(_) => match raw {
+                "1" | "true" => Some(CVarValue::Bool(true)),
+                "0" | "false" => Some(CVarValue::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+
+    fn clamp(self, min: Option<f32>, max: Option<f32>) -> Self {
+        let min = min.unwrap_or(f32::MIN);
+        let max = max.unwrap_or(f32::MAX);
+
+        match self {
+            CVarValue::Float(x) => CVarValue::Float(x.clamp(min, max)),
+            CVarValue::Int(x) => CVarValue::Int((x as f32).clamp(min, max) as i32),
+            CVarValue::Bool(_) => self,
+        }
+    }
+
+    pub fn as_f32(&self) -> f32 {
+        match *self {
+            CVarValue::Float(x) => x,
+            CVarValue::Int(x) => x as f32,
+            CVarValue::Bool(x) => x as i32 as f32,
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        self.as_f32() != 0.
+    }
+}
+
+impl Display for CVarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CVarValue::Float(x) => write!(f, "{x}"),
+            CVarValue::Int(x) => write!(f, "{x}"),
+            CVarValue::Bool(x) => write!(f, "{}", *x as i32),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CVarFlags {
+    /// Can only be changed while `sv_cheats` is enabled.
+    pub cheat: bool,
+    /// Persisted to the config file.
+    pub archive: bool,
+    /// Changing it from its default invalidates the current run.
+    pub notify: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CVar {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: CVarValue,
+    pub value: CVarValue,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub flags: CVarFlags,
+}
+
+impl CVar {
+    pub fn new(name: &'static str, default: CVarValue, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            default,
+            value: default,
+            min: None,
+            max: None,
+            flags: CVarFlags::default(),
+        }
+    }
+
+    pub fn float(name: &'static str, default: f32, description: &'static str) -> Self {
+        Self::new(name, CVarValue::Float(default), description)
+    }
+
+    pub fn int(name: &'static str, default: i32, description: &'static str) -> Self {
+        Self::new(name, CVarValue::Int(default), description)
+    }
+
+    pub fn bool(name: &'static str, default: bool, description: &'static str) -> Self {
+        Self::new(name, CVarValue::Bool(default), description)
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub fn cheat(mut self) -> Self {
+        self.flags.cheat = true;
+        self
+    }
+
+    pub fn archived(mut self) -> Self {
+        self.flags.archive = true;
+        self
+    }
+
+    pub fn notify(mut self) -> Self {
+        self.flags.notify = true;
+        self
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.value == self.default
+    }
+}
+
+impl Display for CVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "\"{}\" = \"{}\" ( def. \"{}\" )",
+            self.name, self.value, self.default
+        )?;
+
+        if let Some(min) = self.min {
+            write!(f, " min. {min}")?;
+        }
+
+        if let Some(max) = self.max {
+            write!(f, " max. {max}")?;
+        }
+
+        if self.flags.cheat {
+            write!(f, " cheat")?;
+        }
+
+        if self.flags.archive {
+            write!(f, " archive")?;
+        }
+
+        if self.flags.notify {
+            write!(f, " notify")?;
+        }
+
+        write!(f, " - {}", self.description)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CVarError {
+    #[error("Unknown cvar \"{0}\".")]
+    Unknown(String),
+    #[error("Invalid value \"{1}\" for \"{0}\".")]
+    InvalidValue(&'static str, String),
+    #[error("Can't change \"{0}\", it is cheat protected. Set sv_cheats 1 first.")]
+    Cheat(&'static str),
+}
+
+/// Registry of typed console variables.
+#[derive(Resource, Default)]
+pub struct CVars(BTreeMap<&'static str, CVar>);
+
+impl CVars {
+    pub fn register(&mut self, cvar: CVar) {
+        if self.0.contains_key(cvar.name) {
+            warn!("CVar {} registered twice.", cvar.name);
+        }
+
+        self.0.insert(cvar.name, cvar);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CVar> {
+        self.0.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CVar> {
+        self.0.values()
+    }
+
+    pub fn float(&self, name: &str) -> f32 {
+        self.0[name].value.as_f32()
+    }
+
+    pub fn bool(&self, name: &str) -> bool {
+        self.0[name].value.as_bool()
+    }
+
+    /// The value of a cvar, unless it is still the default.
+    pub fn overridden(&self, name: &str) -> Option<f32> {
+        self.0
+            .get(name)
+            .filter(|x| !x.is_default())
+            .map(|x| x.value.as_f32())
+    }
+
+    /// Parses and sets the value of a cvar, clamped to its range.
+    pub fn set(&mut self, name: &str, raw: &str) -> Result<CVarValue, CVarError> {
+        let cheats = self.bool(SV_CHEATS);
+
+        let Some(cvar) = self.0.get_mut(name) else {
+            return Err(CVarError::Unknown(name.to_string()));
+        };
+
+        if cvar.flags.cheat && !cheats {
+            return Err(CVarError::Cheat(cvar.name));
+        }
+
+        let Some(value) = cvar.default.parse(raw) else {
+            return Err(CVarError::InvalidValue(cvar.name, raw.to_string()));
+        };
+
+        cvar.value = value.clamp(cvar.min, cvar.max);
+
+        let value = cvar.value;
+
+        // Like Source, disabling cheats reverts every cheat protected cvar.
+        if name == SV_CHEATS && !value.as_bool() {
+            self.0
+                .values_mut()
+                .filter(|x| x.flags.cheat)
+                .for_each(|x| x.value = x.default);
+        }
+
+        Ok(value)
+    }
+}
+
+pub trait AddCVar {
+    fn add_cvar(&mut self, cvar: CVar) -> &mut Self;
+}
+
+impl AddCVar for App {
+    fn add_cvar(&mut self, cvar: CVar) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<CVars>()
+            .register(cvar);
+        self
+    }
+}
+
+/// Makes the console accept every registered cvar as a command.
+fn register_cvar_commands(mut config: ResMut<ConsoleConfiguration>, cvars: Res<CVars>) {
+    for cvar in cvars.iter() {
+        config.commands.insert(
+            cvar.name,
+            Command::new(cvar.name)
+                .no_binary_name(true)
+                .about(cvar.description)
+                .arg(Arg::new("value").index(1)),
+        );
+    }
+}
+
+fn cvar_commands(
+    mut er: EventReader<ConsoleCommandEntered>,
+    mut ew: EventWriter<PrintConsoleLine>,
+    mut cvars: ResMut<CVars>,
+) {
+    for ConsoleCommandEntered { command_name, args } in er.read() {
+        let Some(cvar) = cvars.get(command_name) else {
+            continue;
+        };
+
+        let line = match args.first() {
+            None => cvar.to_string(),
+            Some(raw) => match cvars.set(command_name, raw.trim_matches('"')) {
+                Ok(value) => format!("{command_name} = {value}"),
+                Err(e) => e.to_string(),
+            },
+        };
+
+        ew.write(PrintConsoleLine::new(line.into()));
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "cvarlist")]
+struct CVarListCommand {
+    /// Only list cvars starting with this prefix
+    #[arg(index = 1)]
+    prefix: Option<String>,
+}
+
+fn cvarlist(mut log: ConsoleCommand<CVarListCommand>, cvars: Res<CVars>) {
+    let Some(Ok(CVarListCommand { prefix })) = log.take() else {
+        return;
+    };
+
+    let mut count = 0;
+
+    for cvar in cvars.iter().filter(|x| {
+        prefix
+            .as_ref()
+            .is_none_or(|p| x.name.starts_with(p.as_str()))
+    }) {
+        reply!(log, "{cvar}");
+        count += 1;
+    }
+
+    reply!(log, "{count} total cvars");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CL_TEST: &str = "cl_test";
+    const SV_TEST: &str = "sv_test";
+
+    fn cvars() -> CVars {
+        let mut cvars = CVars::default();
+        cvars.register(CVar::bool(SV_CHEATS, false, ""));
+        cvars.register(CVar::float(CL_TEST, 1., "").with_range(0., 10.));
+        cvars.register(CVar::int(SV_TEST, 5, "").with_range(0., 10.).cheat());
+        cvars
+    }
+
+    #[test]
+    fn set_clamps_to_range() {
+        let mut cvars = cvars();
+
+        assert_eq!(cvars.set(CL_TEST, "20").ok(), Some(CVarValue::Float(10.)));
+        assert_eq!(cvars.set(CL_TEST, "-1").ok(), Some(CVarValue::Float(0.)));
+        assert_eq!(cvars.set(CL_TEST, "2.5").ok(), Some(CVarValue::Float(2.5)));
+        assert!(!cvars.get(CL_TEST).unwrap().is_default());
+    }
+
+    #[test]
+    fn set_rejects_invalid_values() {
+        let mut cvars = cvars();
+
+        for raw in ["", "abc", "nan", "inf"] {
+            assert!(matches!(
+                cvars.set(CL_TEST, raw),
+                Err(CVarError::InvalidValue(..))
+            ));
+        }
+
+        assert!(matches!(
+            cvars.set(SV_CHEATS, "2"),
+            Err(CVarError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            cvars.set("cl_missing", "1"),
+            Err(CVarError::Unknown(_))
+        ));
+        assert_eq!(cvars.float(CL_TEST), 1.);
+    }
+
+    #[test]
+    fn cheats_protect_and_revert() {
+        let mut cvars = cvars();
+
+        assert!(matches!(cvars.set(SV_TEST, "7"), Err(CVarError::Cheat(_))));
+
+        cvars.set(SV_CHEATS, "1").unwrap();
+        assert_eq!(cvars.set(SV_TEST, "7").ok(), Some(CVarValue::Int(7)));
+
+        cvars.set(SV_CHEATS, "0").unwrap();
+        assert_eq!(cvars.float(SV_TEST), 5.);
+        assert!(cvars.get(SV_TEST).unwrap().is_default());
+    }
+}
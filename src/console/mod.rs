@@ -1,3 +1,11 @@
+mod config;
+mod cvar;
+mod stepping;
+
+pub use config::*;
+pub use cvar::*;
+pub use stepping::*;
+
 use std::num::NonZeroUsize;
 
 use bevy::prelude::*;
@@ -13,11 +21,13 @@ pub struct ConsolePlugin;
 impl Plugin for ConsolePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ConsoleConfiguration::default())
+            .add_plugins((CVarPlugin, ConfigPlugin, SteppingPlugin))
             .add_console_command::<ExampleCommand, _>(example_command)
             .add_console_command::<LevelCommand, _>(level)
             .add_console_command::<DebugCommand, _>(debug)
             .add_console_command::<PauseCommand, _>(pause)
             .add_console_command::<NoClipCommand, _>(noclip)
+            .add_console_command::<RestartCommand, _>(restart)
             .add_console_command::<FpsCommand, _>(fps);
     }
 }
@@ -42,7 +52,11 @@ struct LevelCommand {
     level: usize,
 }
 
-fn level(mut log: ConsoleCommand<LevelCommand>, mut ew: EventWriter<SpawnLevel>) {
+fn level(
+    mut log: ConsoleCommand<LevelCommand>,
+    mut ew: EventWriter<SpawnLevel>,
+    mut ew_invalidate: EventWriter<InvalidateRun>,
+) {
     if let Some(Ok(LevelCommand { level })) = log.take() {
         reply!(log, "Loading Level {level}");
 
@@ -60,6 +74,31 @@ fn level(mut log: ConsoleCommand<LevelCommand>, mut ew: EventWriter<SpawnLevel>)
         };
 
         ew.write(SpawnLevel(level));
+        ew_invalidate.write(InvalidateRun(Violation::LevelSkip));
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "restart")]
+struct RestartCommand {}
+
+/// Back to the start of the level, the timer keeps running during a race.
+fn restart(
+    mut log: ConsoleCommand<RestartCommand>,
+    mut history: ResMut<History>,
+    mut timer: ResMut<LevelDuration>,
+    race_state: Res<State<RaceState>>,
+    mut ew: EventWriter<Respawn<LogicalPlayer>>,
+) {
+    let Some(Ok(RestartCommand {})) = log.take() else {
+        return;
+    };
+
+    history.0.clear();
+    ew.write(Respawn::<LogicalPlayer>::new(SPAWN_POINT));
+
+    if *race_state.get() != RaceState::Racing {
+        timer.0.reset();
     }
 }
 
@@ -67,7 +106,11 @@ fn level(mut log: ConsoleCommand<LevelCommand>, mut ew: EventWriter<SpawnLevel>)
 #[command(name = "noclip")]
 struct NoClipCommand {}
 
-fn noclip(mut log: ConsoleCommand<NoClipCommand>, mut q_controller: Query<&mut FpsController>) {
+fn noclip(
+    mut log: ConsoleCommand<NoClipCommand>,
+    mut q_controller: Query<&mut FpsController>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
     let Some(Ok(NoClipCommand {})) = log.take() else {
         return;
     };
@@ -75,7 +118,10 @@ fn noclip(mut log: ConsoleCommand<NoClipCommand>, mut q_controller: Query<&mut F
     for mut controller in &mut q_controller {
         controller.move_mode = match controller.move_mode {
             MoveMode::Noclip => MoveMode::Ground,
-            MoveMode::Ground => MoveMode::Noclip,
+            MoveMode::Ground => {
+                ew.write(InvalidateRun(Violation::Noclip));
+                MoveMode::Noclip
+            }
         }
     }
 }
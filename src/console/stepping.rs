@@ -0,0 +1,121 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_console::*;
+use clap::Parser;
+
+use super::*;
+
+pub const HOST_TIMESCALE: &str = "host_timescale";
+
+pub struct SteppingPlugin;
+
+impl Plugin for SteppingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsStepper>()
+            .add_cvar(
+                CVar::float(
+                    HOST_TIMESCALE,
+                    1.,
+                    "Simulation speed, the fixed timestep stays at the tick rate.",
+                )
+                .with_range(0.1, 2.)
+                .cheat()
+                .notify(),
+            )
+            .add_console_command::<PhysPauseCommand, _>(phys_pause)
+            .add_console_command::<PhysStepCommand, _>(phys_step)
+            .add_systems(Update, apply_timescale.run_if(resource_changed::<CVars>))
+            .add_systems(FixedPreUpdate, step_physics);
+    }
+}
+
+/// Holds the physics paused and lets it advance a number of fixed ticks at a time.
+#[derive(Resource, Debug, Default)]
+pub struct PhysicsStepper {
+    pub paused: bool,
+    pub remaining: u32,
+}
+
+fn apply_timescale(cvars: Res<CVars>, mut time: ResMut<Time<Virtual>>) {
+    let timescale = cvars.float(HOST_TIMESCALE);
+
+    if time.relative_speed() != timescale {
+        time.set_relative_speed(timescale);
+    }
+}
+
+/// Runs every fixed tick before physics, so each requested step is exactly one tick of the physics and the controller.
+fn step_physics(
+    mut stepper: ResMut<PhysicsStepper>,
+    mut time: ResMut<Time<Physics>>,
+    paused: Res<State<PausedState>>,
+) {
+    // The pause menu holds the physics too, steps wait until it is closed.
+    if !stepper.paused || *paused.get() == PausedState::Paused {
+        return;
+    }
+
+    if stepper.remaining == 0 {
+        time.pause();
+        return;
+    }
+
+    stepper.remaining -= 1;
+    time.unpause();
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "phys_pause")]
+struct PhysPauseCommand {}
+
+fn phys_pause(
+    mut log: ConsoleCommand<PhysPauseCommand>,
+    mut stepper: ResMut<PhysicsStepper>,
+    mut time: ResMut<Time<Physics>>,
+    paused: Res<State<PausedState>>,
+    cvars: Res<CVars>,
+) {
+    let Some(Ok(PhysPauseCommand {})) = log.take() else {
+        return;
+    };
+
+    if !stepper.paused && !cvars.bool(SV_CHEATS) {
+        reply!(log, "Can't pause physics, set sv_cheats 1 first.");
+        return;
+    }
+
+    stepper.paused = !stepper.paused;
+    stepper.remaining = 0;
+
+    if stepper.paused {
+        reply!(log, "Physics paused, use phys_step to advance.");
+        return;
+    }
+
+    if *paused.get() == PausedState::Running {
+        time.unpause();
+    }
+
+    reply!(log, "Physics resumed.");
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "phys_step")]
+struct PhysStepCommand {
+    /// Number of fixed ticks to advance
+    #[arg(index = 1, default_value_t = 1)]
+    ticks: u32,
+}
+
+fn phys_step(mut log: ConsoleCommand<PhysStepCommand>, mut stepper: ResMut<PhysicsStepper>) {
+    let Some(Ok(PhysStepCommand { ticks })) = log.take() else {
+        return;
+    };
+
+    if !stepper.paused {
+        reply!(log, "Physics isn't paused, use phys_pause first.");
+        return;
+    }
+
+    stepper.remaining += ticks;
+}
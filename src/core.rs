@@ -1,14 +1,12 @@
 use std::{marker::PhantomData, num::NonZeroUsize};
 
 use avian3d::{PhysicsPlugins, prelude::*};
-use bevy::{
-    asset::{AssetMetaCheck, LoadState},
-    prelude::*,
-};
+use bevy::{asset::LoadState, audio::Volume, prelude::*};
 use bevy_fps_controller::controller::LogicalPlayer;
 use bevy_hanabi::EffectAsset;
 use bevy_skein::SkeinPlugin;
 
+use crate::preset::TickRate;
 pub use crate::state::*;
 
 pub const LEVEL_COUNT: usize = 3;
@@ -39,6 +37,67 @@ pub struct Sounds {
 #[derive(Event)]
 pub struct SpawnLevel(pub NonZeroUsize);
 
+/// Written once the scene of a level is spawned.
+#[derive(Event)]
+pub struct LevelSpawned(pub NonZeroUsize);
+
+/// An entity touched a checkpoint.
+#[derive(Event)]
+pub struct CheckpointReached {
+    pub checkpoint: Entity,
+    pub entity: Entity,
+}
+
+/// An entity touched the end of the level.
+#[derive(Event)]
+pub struct LevelFinished {
+    pub entity: Entity,
+}
+
+/// An entity left a speed boost.
+#[derive(Event)]
+pub struct Boosted {
+    pub boost: Entity,
+    pub entity: Entity,
+}
+
+/// Plays a one-shot particle effect from the pool.
+#[derive(Event)]
+pub struct PlayEffect {
+    pub effect: Handle<EffectAsset>,
+    pub target: EffectTarget,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EffectTarget {
+    /// Follows the entity until the effect is done.
+    Entity(Entity),
+    Translation(Vec3),
+}
+
+/// Plays a one-shot sound from the pool.
+#[derive(Event)]
+pub struct PlaySound {
+    pub sound: Handle<AudioSource>,
+    pub volume: Volume,
+    pub speed: f32,
+}
+
+impl PlaySound {
+    pub fn new(sound: Handle<AudioSource>, volume: f32) -> Self {
+        Self {
+            sound,
+            volume: Volume::Linear(volume),
+            speed: 1.,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+}
+
 #[derive(Event)]
 pub struct Respawn<S: Component> {
     pub translation: Vec3,
@@ -92,6 +151,26 @@ pub struct Prop;
 #[reflect(Component)]
 pub struct Ground;
 
+#[derive(Component, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component, Default)]
+pub enum SurfaceMaterial {
+    #[default]
+    Stone,
+    Metal,
+    Glass,
+    Wood,
+    Grass,
+}
+
+/// The surface the player is currently standing on, `None` while airborne.
+#[derive(Component, Debug, Default)]
+#[require(Stride)]
+pub struct GroundSurface(pub Option<SurfaceMaterial>);
+
+/// Distance travelled on the ground since the last footstep.
+#[derive(Component, Debug, Default)]
+pub struct Stride(pub f32);
+
 #[derive(Component, Reflect, Debug)]
 #[reflect(Component)]
 pub struct CheckPoint;
@@ -107,12 +186,17 @@ pub struct SpeedBoost(pub f32);
 #[derive(Resource, Debug, Default)]
 pub struct History(pub Vec<Entity>);
 
+/// Where players respawn after reaching a checkpoint.
+pub fn respawn_point(checkpoint: &GlobalTransform) -> Vec3 {
+    let t = checkpoint.translation();
+    t.with_z(t.z + 4.)
+}
+
 impl History {
     pub fn last(&self, q_gtf: Query<&GlobalTransform, With<CheckPoint>>) -> Vec3 {
         if let Some(check_point) = self.0.last() {
             if let Ok(gtf) = q_gtf.get(*check_point) {
-                let t = gtf.translation();
-                return t.with_z(t.z + 4.);
+                return respawn_point(gtf);
             }
         };
 
@@ -135,6 +219,7 @@ pub struct ParticleEffects {
     pub boost_fx: Handle<EffectAsset>,
     pub boost_idle_fx: Handle<EffectAsset>,
     pub player_boost_fx: Handle<EffectAsset>,
+    pub speed_trail_fx: Handle<EffectAsset>,
     pub new_level_fx: [Handle<EffectAsset>; LEVEL_COUNT],
     pub checkpoint_fx: [Handle<EffectAsset>; LEVEL_COUNT],
 }
@@ -154,8 +239,14 @@ pub struct CorePlugin;
 impl Plugin for CorePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnLevel>()
+            .add_event::<LevelSpawned>()
+            .add_event::<CheckpointReached>()
+            .add_event::<LevelFinished>()
+            .add_event::<Boosted>()
+            .add_event::<PlayEffect>()
+            .add_event::<PlaySound>()
             .insert_resource(AssetsLoading::default())
-            .insert_resource(Time::<Fixed>::from_hz(128.0))
+            .insert_resource(Time::<Fixed>::from_duration(TickRate::default().timestep()))
             .insert_resource(History::default())
             .register_type::<Prop>()
             .register_type::<Character>()
@@ -166,25 +257,8 @@ impl Plugin for CorePlugin {
             .register_type::<End>()
             .register_type::<SpeedBoost>()
             .register_type::<Ground>()
+            .register_type::<SurfaceMaterial>()
             .add_plugins((
-                DefaultPlugins
-                    .set(AssetPlugin {
-                        // Wasm builds will check for meta files (that don't exist) if this isn't set.
-                        // This causes errors and even panics in web builds on itch.
-                        // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
-                        meta_check: AssetMetaCheck::Never,
-                        ..default()
-                    })
-                    .set(WindowPlugin {
-                        primary_window: Window {
-                            fit_canvas_to_parent: true,
-                            // TODO experiment with VSync off and frame limiting
-                            present_mode: default(),
-                            ..default()
-                        }
-                        .into(),
-                        ..default()
-                    }),
                 SkeinPlugin::default(),
                 PhysicsPlugins::default(),
                 PhysicsDebugPlugin::default(),
@@ -1,19 +1,27 @@
 use bevy::{input::mouse::MouseWheel, prelude::*, window::CursorGrabMode};
 
+use bevy_console::*;
 use bevy_fps_controller::controller::*;
 
 use avian_pickup::prelude::*;
+use clap::Parser;
 
-use crate::core::*;
+use crate::{console::*, core::*, integrity::*, mouse::*, player::MovementSet, records::*};
 
 pub struct InputPlugin;
 
 #[derive(Component)]
 pub struct AutoJump;
 
+/// A jump press or wheel notch, held until a tick of the controller jumped with it.
+#[derive(Resource, Default)]
+struct QueuedJump(bool);
+
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((AvianPickupPlugin::default(), FpsControllerPlugin))
+        app.add_plugins(AvianPickupPlugin::default())
+            .init_resource::<QueuedJump>()
+            .add_console_command::<AutoBhopCommand, _>(autobhop)
             .add_systems(
                 Update,
                 (
@@ -22,20 +30,28 @@ impl Plugin for InputPlugin {
                     handle_auto_jump,
                     handle_reset.before(respawn::<LogicalPlayer>),
                 )
-                    .in_set(GameplaySet),
+                    .in_set(GameplaySet)
+                    .run_if(in_state(ChatState::Closed)),
             )
             .add_systems(OnEnter(AppState::GameOver), enable_cursor)
             .add_systems(OnEnter(PausedState::Paused), enable_cursor)
             .add_systems(OnEnter(AppState::InGame), disable_cursor)
+            .add_systems(OnEnter(RaceState::Finished), enable_cursor)
+            .add_systems(
+                OnExit(RaceState::Finished),
+                disable_cursor.run_if(in_state(AppState::InGame)),
+            )
             .add_systems(
                 OnEnter(PausedState::Running),
                 disable_cursor.run_if(in_state(AppState::InGame)),
             )
             .add_systems(
                 PreUpdate,
-                auto_jump
-                    .after(fps_controller_input)
-                    .before(fps_controller_move),
+                (scroll_jump, auto_jump).chain().after(fps_controller_input),
+            )
+            .add_systems(
+                FixedUpdate,
+                consume_jump.after(fps_controller_move).in_set(MovementSet),
             )
             .add_systems(
                 RunFixedMainLoop,
@@ -47,6 +63,7 @@ impl Plugin for InputPlugin {
 }
 
 fn manage_cursor(
+    cvars: Res<CVars>,
     btn: Res<ButtonInput<MouseButton>>,
     key: Res<ButtonInput<KeyCode>>,
     window_query: Query<&mut Window>,
@@ -54,7 +71,7 @@ fn manage_cursor(
     mut ns: ResMut<NextState<PausedState>>,
 ) {
     if btn.just_pressed(MouseButton::Left) {
-        disable_cursor(window_query, controller_query);
+        disable_cursor(cvars, window_query, controller_query);
     }
 
     if key.just_pressed(KeyCode::Escape) {
@@ -63,11 +80,15 @@ fn manage_cursor(
 }
 
 fn disable_cursor(
+    cvars: Res<CVars>,
     mut window_query: Query<&mut Window>,
     mut controller_query: Query<&mut FpsController>,
 ) {
     for mut window in &mut window_query {
-        window.cursor_options.grab_mode = CursorGrabMode::Locked;
+        window.cursor_options.grab_mode = grab_mode(&cvars);
+        // Without raw input the view turns by how far the cursor leaves the center.
+        let center = window.size() / 2.;
+        window.set_cursor_position(Some(center));
         window.cursor_options.visible = false;
         for mut controller in &mut controller_query {
             controller.enable_input = true;
@@ -143,10 +164,54 @@ fn auto_jump(mut q_input: Query<&mut FpsControllerInput, With<AutoJump>>) {
     }
 }
 
+/// Without auto-bhop every jump needs a new press or a wheel notch, holding the key only jumps once.
+fn scroll_jump(
+    category: Res<RunCategory>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut er: EventReader<MouseWheel>,
+    mut queued: ResMut<QueuedJump>,
+    mut q_input: Query<(&FpsController, &mut FpsControllerInput), With<LogicalPlayer>>,
+) {
+    let scrolled = er.read().any(|x| x.y < 0.);
+
+    if category.auto_bhop {
+        return;
+    }
+
+    for (controller, mut input) in &mut q_input {
+        if !controller.enable_input {
+            continue;
+        }
+
+        // Frames without a fixed tick would drop the press otherwise.
+        queued.0 |= keys.just_pressed(KeyCode::Space) || scrolled;
+        input.jump = queued.0;
+    }
+}
+
+/// A queued jump is only used by one tick, later ticks of the same frame don't jump again.
+fn consume_jump(
+    category: Res<RunCategory>,
+    mut queued: ResMut<QueuedJump>,
+    mut q_input: Query<&mut FpsControllerInput, (With<LogicalPlayer>, Without<AutoJump>)>,
+) {
+    if category.auto_bhop {
+        return;
+    }
+
+    queued.0 = false;
+
+    for mut input in &mut q_input {
+        input.jump = false;
+    }
+}
+
+/// Jumping on its own is a practice tool, the run no longer counts.
 fn handle_auto_jump(
     mut cmd: Commands,
     q_player: Query<(Entity, Option<&AutoJump>), With<FpsControllerInput>>,
     keys: Res<ButtonInput<KeyCode>>,
+    mut ew: EventWriter<InvalidateRun>,
 ) {
     {
         if !keys.just_pressed(KeyCode::Space) || !keys.pressed(KeyCode::ShiftLeft) {
@@ -160,10 +225,47 @@ fn handle_auto_jump(
             };
 
             cmd.entity(player).insert(AutoJump);
+            ew.write(InvalidateRun(Violation::Practice));
         }
     }
 }
 
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "autobhop")]
+struct AutoBhopCommand {
+    #[arg(value_parser = clap::builder::BoolishValueParser::new())]
+    enabled: Option<bool>,
+}
+
+fn autobhop(
+    mut log: ConsoleCommand<AutoBhopCommand>,
+    mut category: ResMut<RunCategory>,
+    app_state: Res<State<AppState>>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(AutoBhopCommand { enabled })) = log.take() else {
+        return;
+    };
+
+    let Some(enabled) = enabled else {
+        reply!(log, "autobhop: {}", category.auto_bhop as i32);
+        return;
+    };
+
+    if category.auto_bhop == enabled {
+        return;
+    }
+
+    category.auto_bhop = enabled;
+
+    // The run so far was played with the other setting.
+    if *app_state.get() == AppState::InGame {
+        ew.write(InvalidateRun(Violation::CategoryChanged));
+    }
+
+    reply!(log, "autobhop: {}", enabled as i32);
+}
+
 fn handle_reset(
     keys: Res<ButtonInput<KeyCode>>,
     mut ew: EventWriter<Respawn<LogicalPlayer>>,
@@ -0,0 +1,124 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{input::mouse::MouseWheel, prelude::*};
+use bevy_fps_controller::controller::*;
+
+use crate::prelude::*;
+
+pub const CL_SHOWKEYS: &str = "cl_showkeys";
+
+/// Collects the keys of the local or spectated player for the input display overlay.
+pub struct InputDisplayPlugin;
+
+impl Plugin for InputDisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputDisplay>()
+            .add_cvar(
+                CVar::bool(
+                    CL_SHOWKEYS,
+                    false,
+                    "Show the movement keys, jumps and mouse direction.",
+                )
+                .archived(),
+            )
+            .add_systems(Update, track_input_display.in_set(GameplaySet));
+    }
+}
+
+/// The input shown by the overlay this frame.
+///
+/// Fed by the local player or the spectated one, anything else replaying inputs can [`show`](Self::show) them too.
+#[derive(Resource, Debug, Default)]
+pub struct InputDisplay {
+    pub keys: InputKeys,
+    /// Yaw turned this frame in radians, positive turning left.
+    pub turn: f32,
+    /// How the shown player pressed its latest jump, replicated along with the keys.
+    pub jump_kind: JumpKind,
+    yaw: f32,
+    /// The spectated player, `None` for the local one.
+    source: Option<PlayerId>,
+}
+
+impl InputDisplay {
+    /// Shows `keys` at the view of `yaw`, the mouse direction is the change of the yaw since the last frame of the same source.
+    pub fn show(&mut self, source: Option<PlayerId>, keys: InputKeys, yaw: f32) {
+        self.turn = match self.source == source {
+            true => (yaw - self.yaw + PI).rem_euclid(TAU) - PI,
+            false => 0.,
+        };
+
+        // Another player's jumps aren't known until it jumps.
+        if self.source != source {
+            self.jump_kind = default();
+        }
+
+        if let Some(jump) = keys.jump {
+            self.jump_kind = jump;
+        }
+
+        self.keys = keys;
+        self.yaw = yaw;
+        self.source = source;
+    }
+}
+
+/// Spectators see the replicated keys of the player they follow.
+fn track_input_display(
+    mut display: ResMut<InputDisplay>,
+    spectator: Res<Spectator>,
+    spectator_state: Res<State<SpectatorState>>,
+    q_remote: Query<(&RemotePlayer, &ReplicatedState)>,
+    q_player: Query<(&FpsController, &FpsControllerInput, Has<AutoJump>), With<LogicalPlayer>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut er_wheel: EventReader<MouseWheel>,
+) {
+    let scrolled = er_wheel.read().any(|x| x.y < 0.);
+
+    if *spectator_state.get() == SpectatorState::Spectating {
+        if let Some((_, state)) = spectated_state(&spectator, &q_remote) {
+            display.show(spectator.target, state.keys, state.yaw);
+        }
+        return;
+    }
+
+    let Ok((controller, input, auto_jump)) = q_player.single() else {
+        return;
+    };
+
+    // The controller input only holds a jump until a tick used it, the display follows the keys instead.
+    let jump = match (auto_jump, scrolled, keys.pressed(KeyCode::Space)) {
+        _ if !controller.enable_input => None,
+        (true, _, _) => Some(JumpKind::Auto),
+        (false, true, _) => Some(JumpKind::Scroll),
+        (false, false, true) => Some(JumpKind::Key),
+        (false, false, false) => None,
+    };
+
+    display.show(
+        None,
+        InputKeys::new(input.movement, jump, input.crouch),
+        input.yaw,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_kind_follows_the_source() {
+        let keys = |jump| InputKeys::new(Vec3::ZERO, jump, false);
+        let mut display = InputDisplay::default();
+
+        display.show(Some(1), keys(Some(JumpKind::Scroll)), 0.);
+        assert_eq!(display.jump_kind, JumpKind::Scroll);
+
+        // Kept between jumps.
+        display.show(Some(1), keys(None), 0.);
+        assert_eq!(display.jump_kind, JumpKind::Scroll);
+
+        display.show(Some(2), keys(None), 0.);
+        assert_eq!(display.jump_kind, JumpKind::Key);
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+
+use crate::{console::*, core::*, net::Deviation};
+
+pub struct IntegrityPlugin;
+
+impl Plugin for IntegrityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunIntegrity>()
+            .add_event::<InvalidateRun>()
+            .add_systems(OnEnter(AppState::InGame), reset_run_integrity)
+            .add_systems(
+                Update,
+                (check_cvars, invalidate_run)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Why a run can't count towards records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    Practice,
+    Noclip,
+    LevelSkip,
+    /// The style or another option of the run changed after it started.
+    CategoryChanged,
+    CVar(&'static str),
+    /// Flagged by the server's movement validation.
+    Movement(Deviation),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Practice => write!(f, "practice tools"),
+            Violation::Noclip => write!(f, "noclip"),
+            Violation::LevelSkip => write!(f, "level skip"),
+            Violation::CategoryChanged => write!(f, "category changed"),
+            Violation::CVar(name) => write!(f, "{name} changed"),
+            Violation::Movement(deviation) => write!(f, "{deviation}"),
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct InvalidateRun(pub Violation);
+
+/// Tracks everything the current run used that it shouldn't have.
+///
+/// Personal bests and leaderboards only accept runs that are still valid.
+#[derive(Resource, Debug, Default)]
+pub struct RunIntegrity(Vec<Violation>);
+
+impl RunIntegrity {
+    pub fn is_valid(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Practice only runs are labeled as such, anything else invalidates the run.
+    pub fn badge(&self) -> Option<&'static str> {
+        match self.0.as_slice() {
+            [] => None,
+            [Violation::Practice] => Some("Practice"),
+            _ => Some("Invalid"),
+        }
+    }
+
+    pub fn violations(&self) -> impl Iterator<Item = &Violation> {
+        self.0.iter()
+    }
+
+    fn flag(&mut self, violation: Violation) -> bool {
+        if self.0.contains(&violation) {
+            return false;
+        }
+
+        self.0.push(violation);
+        true
+    }
+}
+
+fn reset_run_integrity(mut integrity: ResMut<RunIntegrity>) {
+    integrity.0.clear();
+}
+
+/// Runs started or played with changed gameplay cvars are invalid, even if they are reverted later.
+fn check_cvars(cvars: Res<CVars>, mut ew: EventWriter<InvalidateRun>) {
+    for cvar in cvars.iter().filter(|x| x.flags.notify && !x.is_default()) {
+        ew.write(InvalidateRun(Violation::CVar(cvar.name)));
+    }
+}
+
+fn invalidate_run(mut er: EventReader<InvalidateRun>, mut integrity: ResMut<RunIntegrity>) {
+    for InvalidateRun(violation) in er.read() {
+        if integrity.flag(*violation) {
+            info!("Run invalidated: {violation}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge() {
+        let mut integrity = RunIntegrity::default();
+        assert_eq!(integrity.badge(), None);
+        assert!(integrity.is_valid());
+
+        assert!(integrity.flag(Violation::Practice));
+        assert!(!integrity.flag(Violation::Practice));
+        assert_eq!(integrity.badge(), Some("Practice"));
+        assert!(!integrity.is_valid());
+
+        integrity.flag(Violation::Noclip);
+        assert_eq!(integrity.badge(), Some("Invalid"));
+
+        let mut integrity = RunIntegrity::default();
+        integrity.flag(Violation::CVar(SV_CHEATS));
+        assert_eq!(integrity.badge(), Some("Invalid"));
+    }
+}
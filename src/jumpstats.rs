@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::prelude::*;
+
+pub const CL_SHOWJUMPSTATS: &str = "cl_showjumpstats";
+
+/// Older jumps are dropped.
+const JUMP_HISTORY: usize = 32;
+
+/// Tracks the speed, distance and strafes of every jump of the local player.
+pub struct JumpStatsPlugin;
+
+impl Plugin for JumpStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JumpStats>()
+            .add_cvar(
+                CVar::bool(CL_SHOWJUMPSTATS, false, "Show the stats of the last jump.").archived(),
+            )
+            .add_console_command::<JumpStatsCommand, _>(jumpstats)
+            .add_systems(Update, track_jumps.in_set(GameplaySet))
+            .add_systems(OnExit(AppState::InGame), reset_jump_stats);
+    }
+}
+
+/// The keys pressed during a strafe, and what it did to the speed.
+#[derive(Debug, Clone, Default)]
+pub struct Strafe {
+    /// Negative while strafing left.
+    pub direction: f32,
+    pub gain: f32,
+    pub loss: f32,
+    synced: u32,
+    turning: u32,
+}
+
+impl Strafe {
+    /// Share of the frames spent turning in the direction of the strafe key.
+    pub fn sync(&self) -> f32 {
+        match self.turning {
+            0 => 0.,
+            x => self.synced as f32 / x as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Jump {
+    pub takeoff_speed: f32,
+    pub landing_speed: f32,
+    /// Highest point above the takeoff.
+    pub height: f32,
+    /// Horizontal distance from the takeoff to the landing.
+    pub distance: f32,
+    pub strafes: Vec<Strafe>,
+    takeoff: Vec3,
+}
+
+impl Jump {
+    fn new(takeoff: Vec3, takeoff_speed: f32) -> Self {
+        Self {
+            takeoff_speed,
+            landing_speed: takeoff_speed,
+            height: 0.,
+            distance: 0.,
+            strafes: Vec::new(),
+            takeoff,
+        }
+    }
+
+    /// Sync over all strafes of the jump.
+    pub fn sync(&self) -> f32 {
+        let turning = self.strafes.iter().map(|x| x.turning).sum::<u32>();
+        let synced = self.strafes.iter().map(|x| x.synced).sum::<u32>();
+
+        match turning {
+            0 => 0.,
+            x => synced as f32 / x as f32,
+        }
+    }
+}
+
+impl std::fmt::Display for Jump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Pre {:.2}  Speed {:.2}  Distance {:.2}  Height {:.2}  Strafes {}  Sync {:.0}%",
+            self.takeoff_speed,
+            self.landing_speed,
+            self.distance,
+            self.height,
+            self.strafes.len(),
+            self.sync() * 100.
+        )?;
+
+        for (i, strafe) in self.strafes.iter().enumerate() {
+            write!(
+                f,
+                "\n{} {}: +{:.2} -{:.2} {:.0}%",
+                i + 1,
+                match strafe.direction < 0. {
+                    true => "A",
+                    false => "D",
+                },
+                strafe.gain,
+                strafe.loss,
+                strafe.sync() * 100.
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct JumpStats {
+    /// Oldest first.
+    jumps: VecDeque<Jump>,
+    current: Option<Jump>,
+    grounded: bool,
+    speed: f32,
+    yaw: f32,
+}
+
+impl JumpStats {
+    pub fn last(&self) -> Option<&Jump> {
+        self.jumps.back()
+    }
+
+    /// Oldest first.
+    pub fn jumps(&self) -> impl DoubleEndedIterator<Item = &Jump> {
+        self.jumps.iter()
+    }
+
+    fn land(&mut self, jump: Jump) {
+        if self.jumps.len() >= JUMP_HISTORY {
+            self.jumps.pop_front();
+        }
+        self.jumps.push_back(jump);
+    }
+}
+
+/// A jump starts when the player leaves the ground upwards and ends on the next landing.
+fn track_jumps(
+    mut stats: ResMut<JumpStats>,
+    q_player: Query<
+        (
+            &FpsController,
+            &FpsControllerInput,
+            &Transform,
+            &LinearVelocity,
+        ),
+        With<LogicalPlayer>,
+    >,
+    mut er_respawn: EventReader<Respawn<LogicalPlayer>>,
+    mut ew: EventWriter<PrintConsoleLine>,
+) {
+    let Ok((controller, input, transform, velocity)) = q_player.single() else {
+        return;
+    };
+
+    let grounded = controller.ground_tick > 0;
+    let speed = velocity.xz().length();
+    let speed_delta = speed - stats.speed;
+    let yaw_delta = input.yaw - stats.yaw;
+    let was_grounded = stats.grounded;
+
+    stats.grounded = grounded;
+    stats.speed = speed;
+    stats.yaw = input.yaw;
+
+    // Respawns and noclip aren't jumps.
+    if er_respawn.read().count() > 0 || matches!(controller.move_mode, MoveMode::Noclip) {
+        stats.current = None;
+        return;
+    }
+
+    // The jump input only lasts a tick, leaving the ground upwards is a jump, walking off an edge isn't.
+    if was_grounded && !grounded && velocity.y > 0. {
+        stats.current = Some(Jump::new(transform.translation, speed));
+        return;
+    }
+
+    let Some(jump) = stats.current.as_mut() else {
+        return;
+    };
+
+    jump.height = jump.height.max(transform.translation.y - jump.takeoff.y);
+    jump.landing_speed = speed;
+    jump.distance = (transform.translation - jump.takeoff).xz().length();
+
+    if grounded {
+        let Some(jump) = stats.current.take() else {
+            return;
+        };
+
+        for line in jump.to_string().lines() {
+            ew.write(PrintConsoleLine::new(line.to_string().into()));
+        }
+
+        stats.land(jump);
+        return;
+    }
+
+    let direction = input.movement.x.signum();
+
+    if input.movement.x != 0. && jump.strafes.last().is_none_or(|x| x.direction != direction) {
+        jump.strafes.push(Strafe {
+            direction,
+            ..default()
+        });
+    }
+
+    let Some(strafe) = jump.strafes.last_mut().filter(|_| input.movement.x != 0.) else {
+        return;
+    };
+
+    match speed_delta > 0. {
+        true => strafe.gain += speed_delta,
+        false => strafe.loss -= speed_delta,
+    }
+
+    // The yaw grows turning left, which the left strafe key has to go along with.
+    if yaw_delta != 0. {
+        strafe.turning += 1;
+
+        if yaw_delta * input.movement.x < 0. {
+            strafe.synced += 1;
+        }
+    }
+}
+
+fn reset_jump_stats(mut stats: ResMut<JumpStats>) {
+    stats.current = None;
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "jumpstats")]
+struct JumpStatsCommand {
+    /// Number of the latest jumps to print
+    #[arg(default_value_t = 5)]
+    count: usize,
+}
+
+fn jumpstats(mut log: ConsoleCommand<JumpStatsCommand>, stats: Res<JumpStats>) {
+    let Some(Ok(JumpStatsCommand { count })) = log.take() else {
+        return;
+    };
+
+    if stats.jumps.is_empty() {
+        reply!(log, "No jumps yet.");
+        return;
+    }
+
+    let skip = stats.jumps.len().saturating_sub(count);
+
+    for (i, jump) in stats.jumps().enumerate().skip(skip) {
+        reply!(log, "Jump {}: {jump}", i + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strafe(synced: u32, turning: u32) -> Strafe {
+        Strafe {
+            synced,
+            turning,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn sync() {
+        assert_eq!(strafe(0, 0).sync(), 0.);
+        assert_eq!(strafe(3, 4).sync(), 0.75);
+
+        let mut jump = Jump::new(Vec3::ZERO, 0.);
+        assert_eq!(jump.sync(), 0.);
+
+        // Weighted by the frames of each strafe, not averaged over the strafes.
+        jump.strafes = vec![strafe(1, 1), strafe(1, 3)];
+        assert_eq!(jump.sync(), 0.5);
+    }
+
+    #[test]
+    fn keeps_the_latest_jumps() {
+        let mut stats = JumpStats::default();
+
+        for i in 0..JUMP_HISTORY + 2 {
+            stats.land(Jump::new(Vec3::ZERO, i as f32));
+        }
+
+        assert_eq!(stats.jumps().count(), JUMP_HISTORY);
+        assert_eq!(stats.jumps().next().unwrap().takeoff_speed, 2.);
+        assert_eq!(
+            stats.last().unwrap().takeoff_speed,
+            (JUMP_HISTORY + 1) as f32
+        );
+    }
+}
@@ -0,0 +1,27 @@
+mod audio;
+mod chat;
+mod color;
+mod console;
+mod core;
+mod duration;
+mod input;
+mod input_display;
+mod integrity;
+mod jumpstats;
+mod loading;
+mod mouse;
+mod net;
+mod particle;
+mod player;
+mod pool;
+mod practice;
+mod preset;
+mod records;
+mod spectator;
+mod state;
+mod strafe_trainer;
+mod style;
+mod ui;
+mod world;
+
+pub mod prelude;
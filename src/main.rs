@@ -1,34 +1,50 @@
-mod audio;
-mod color;
-mod console;
-mod core;
-mod duration;
-mod input;
-mod loading;
-mod particle;
-mod player;
-mod prelude;
-mod state;
-mod ui;
-mod world;
-
-use bevy::prelude::*;
-use prelude::*;
+use bevy::{asset::AssetMetaCheck, prelude::*};
+use bevy_hop::prelude::*;
 
 fn main() {
     App::new()
         .add_plugins((
+            DefaultPlugins
+                .set(AssetPlugin {
+                    // Wasm builds will check for meta files (that don't exist) if this isn't set.
+                    // This causes errors and even panics in web builds on itch.
+                    // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
+                    meta_check: AssetMetaCheck::Never,
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: Window {
+                        title: String::from("Bevy Hop"),
+                        fit_canvas_to_parent: true,
+                        // TODO experiment with VSync off and frame limiting
+                        present_mode: default(),
+                        ..default()
+                    }
+                    .into(),
+                    ..default()
+                }),
             CorePlugin,
             StatePlugin,
-            ParticlePlugin,
             WorldPlugin,
             PlayerPlugin,
             DurationPlugin,
             InputPlugin,
-            UiPlugin,
+            // Presentation, left out by the dedicated server.
+            (ParticlePlugin, EnvironmentPlugin, UiPlugin, AudioPlugin),
             ConsolePlugin,
-            AudioPlugin,
             LoadingPlugin,
+            (PoolPlugin, PracticePlugin, IntegrityPlugin, RecordsPlugin),
+            (
+                NetPlugin,
+                SpectatorPlugin,
+                ChatPlugin,
+                StylePlugin,
+                PresetPlugin,
+                MousePlugin,
+                JumpStatsPlugin,
+                StrafeTrainerPlugin,
+                InputDisplayPlugin,
+            ),
         ))
         .run();
 }
@@ -0,0 +1,246 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::{InputSystem, mouse::AccumulatedMouseMotion},
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::prelude::*;
+
+pub const SENSITIVITY: &str = "sensitivity";
+pub const M_YAW: &str = "m_yaw";
+pub const M_PITCH: &str = "m_pitch";
+pub const M_RAWINPUT: &str = "m_rawinput";
+pub const M_DPI: &str = "m_dpi";
+
+/// Degrees per count and sensitivity, the same as Source.
+const DEFAULT_M_YAW: f32 = 0.022;
+
+const CM_PER_INCH: f32 = 2.54;
+
+/// Keeps the view from flipping over when looking straight up or down.
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.002;
+
+/// Source style mouse sensitivity, replacing the sensitivity of the [`FpsController`].
+pub struct MousePlugin;
+
+impl Plugin for MousePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MouseLook>()
+            .add_cvar(
+                // 2.6 turns about as fast as the 0.001 radians per count the controller used.
+                CVar::float(
+                    SENSITIVITY,
+                    2.6,
+                    "Mouse sensitivity, multiplied by m_yaw and m_pitch.",
+                )
+                .with_range(0.01, 100.)
+                .archived(),
+            )
+            .add_cvar(
+                CVar::float(
+                    M_YAW,
+                    DEFAULT_M_YAW,
+                    "Degrees of yaw per count and sensitivity.",
+                )
+                .with_range(0.0001, 1.)
+                .archived(),
+            )
+            .add_cvar(
+                CVar::float(
+                    M_PITCH,
+                    DEFAULT_M_YAW,
+                    "Degrees of pitch per count and sensitivity.",
+                )
+                .with_range(0.0001, 1.)
+                .archived(),
+            )
+            .add_cvar(
+                CVar::bool(
+                    M_RAWINPUT,
+                    true,
+                    "Read the mouse directly instead of the cursor moved by the OS with its acceleration.",
+                )
+                .archived(),
+            )
+            .add_cvar(
+                CVar::int(
+                    M_DPI,
+                    800,
+                    "Counts per inch of the mouse, only used for cm/360.",
+                )
+                .with_range(100., 50000.)
+                .archived(),
+            )
+            .add_console_command::<Cm360Command, _>(cm360)
+            .add_systems(
+                PreUpdate,
+                (
+                    (
+                        mute_controller_look,
+                        read_mouse.after(InputSystem),
+                        follow_rawinput.run_if(resource_changed::<CVars>),
+                    )
+                        .before(fps_controller_input),
+                    apply_mouse_look
+                        .after(fps_controller_input)
+                        .before(fps_controller_look),
+                ),
+            );
+    }
+}
+
+/// How far the mouse turned the view this frame in radians, yaw in `x` and pitch in `y`.
+#[derive(Resource, Debug, Default)]
+pub struct MouseLook(pub Vec2);
+
+/// Centimeters the mouse moves for a full turn.
+pub fn cm_per_360(cvars: &CVars) -> f32 {
+    360. / (cvars.float(SENSITIVITY) * cvars.float(M_YAW) * cvars.float(M_DPI)) * CM_PER_INCH
+}
+
+/// The sensitivity for a full turn in `cm`, at the current `m_yaw` and dpi.
+pub fn sensitivity_for_cm_per_360(cvars: &CVars, cm: f32) -> f32 {
+    360. / (cm / CM_PER_INCH * cvars.float(M_YAW) * cvars.float(M_DPI))
+}
+
+/// The controller doesn't turn by itself, see `apply_mouse_look`.
+fn mute_controller_look(mut q_controller: Query<&mut FpsController, Added<LogicalPlayer>>) {
+    for mut controller in &mut q_controller {
+        controller.sensitivity = 0.;
+    }
+}
+
+/// How the cursor is grabbed while playing.
+/// A locked cursor doesn't move, so without raw input it is confined and recentered instead.
+pub fn grab_mode(cvars: &CVars) -> CursorGrabMode {
+    match cvars.bool(M_RAWINPUT) {
+        true => CursorGrabMode::Locked,
+        false => CursorGrabMode::Confined,
+    }
+}
+
+/// How far the cursor moved away from the center of the window since it was recentered.
+fn cursor_counts(window: &Window) -> Vec2 {
+    // Counts are physical pixels, the cursor position is logical.
+    window.cursor_position().map_or(Vec2::ZERO, |x| {
+        (x - window.size() / 2.) * window.scale_factor()
+    })
+}
+
+fn read_mouse(
+    cvars: Res<CVars>,
+    motion: Res<AccumulatedMouseMotion>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    mut look: ResMut<MouseLook>,
+) {
+    // Raw input comes straight from the device, the cursor moves with the acceleration of the OS.
+    let counts = match (cvars.bool(M_RAWINPUT), q_window.single_mut()) {
+        (true, _) | (false, Err(_)) => motion.delta,
+        (false, Ok(mut window)) => match window.cursor_options.grab_mode {
+            CursorGrabMode::None => Vec2::ZERO,
+            _ => {
+                let counts = cursor_counts(&window);
+                let center = window.size() / 2.;
+                window.set_cursor_position(Some(center));
+                counts
+            }
+        },
+    };
+
+    let sensitivity = cvars.float(SENSITIVITY);
+
+    look.0 = Vec2::new(
+        (counts.x * sensitivity * cvars.float(M_YAW)).to_radians(),
+        (counts.y * sensitivity * cvars.float(M_PITCH)).to_radians(),
+    );
+}
+
+/// Switches the grab of a grabbed cursor when `m_rawinput` changes while playing.
+fn follow_rawinput(cvars: Res<CVars>, mut q_window: Query<&mut Window, With<PrimaryWindow>>) {
+    for mut window in &mut q_window {
+        let mode = grab_mode(&cvars);
+        if window.cursor_options.grab_mode != CursorGrabMode::None
+            && window.cursor_options.grab_mode != mode
+        {
+            window.cursor_options.grab_mode = mode;
+        }
+    }
+}
+
+/// Turns the local player while it takes input.
+pub fn apply_mouse_look(
+    look: Res<MouseLook>,
+    mut q_input: Query<(&FpsController, &mut FpsControllerInput), With<LogicalPlayer>>,
+) {
+    for (controller, mut input) in &mut q_input {
+        if !controller.enable_input {
+            continue;
+        }
+
+        input.yaw -= look.0.x;
+        input.pitch = (input.pitch - look.0.y).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "cm360")]
+struct Cm360Command {
+    /// Sets the sensitivity so a full turn takes this many centimeters
+    cm: Option<f32>,
+}
+
+fn cm360(mut log: ConsoleCommand<Cm360Command>, mut cvars: ResMut<CVars>) {
+    let Some(Ok(Cm360Command { cm })) = log.take() else {
+        return;
+    };
+
+    if let Some(cm) = cm {
+        if cm <= 0. {
+            reply!(log, "A full turn has to take more than 0 cm.");
+            return;
+        }
+
+        let sensitivity = sensitivity_for_cm_per_360(&cvars, cm);
+
+        if let Err(e) = cvars.set(SENSITIVITY, &sensitivity.to_string()) {
+            reply!(log, "{e}");
+            return;
+        }
+    }
+
+    reply!(
+        log,
+        "{:.1} cm/360 at {} dpi (sensitivity {}, m_yaw {})",
+        cm_per_360(&cvars),
+        cvars.float(M_DPI),
+        cvars.float(SENSITIVITY),
+        cvars.float(M_YAW)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::window::WindowResolution;
+
+    use super::*;
+
+    #[test]
+    fn cursor_counts_from_center() {
+        let mut window = Window {
+            resolution: WindowResolution::new(800., 600.),
+            ..default()
+        };
+        assert_eq!(cursor_counts(&window), Vec2::ZERO);
+
+        window.set_cursor_position(Some(Vec2::new(410., 295.)));
+        assert_eq!(cursor_counts(&window), Vec2::new(10., -5.));
+
+        window.set_cursor_position(Some(window.size() / 2.));
+        assert_eq!(cursor_counts(&window), Vec2::ZERO);
+    }
+}
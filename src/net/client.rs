@@ -0,0 +1,529 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    num::NonZeroUsize,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_fps_controller::controller::*;
+
+use crate::{
+    chat::*, color::*, core::*, duration::*, input_display::InputDisplay, integrity::*, player::*,
+    records::*, world::*,
+};
+
+use super::*;
+
+const CONNECT_RETRY: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The local state is sent every n-th fixed tick, 64 Hz at the default 128 Hz tickrate.
+const SEND_INTERVAL: u32 = 2;
+
+/// Inputs sent with every update, several send intervals worth to cover lost packets.
+pub(super) const REDUNDANT_INPUTS: usize = 16;
+
+/// Remote players are rendered this far in the past, so there is a snapshot on either side to interpolate between.
+const INTERPOLATION_DELAY: f32 = 0.1;
+
+const REMOTE_STATE_BUFFER: usize = 32;
+
+pub struct ClientPlugin;
+
+impl Plugin for ClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClientDisconnected>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    (client_connect, client_receive, client_timeout).chain(),
+                    report_level_times,
+                )
+                    .run_if(resource_exists::<Client>),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_disconnect.run_if(on_event::<ClientDisconnected>),
+                    interpolate_remote_players,
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                client_send
+                    .in_set(MultiplayerSet)
+                    .run_if(resource_exists::<Client>),
+            );
+    }
+}
+
+/// Ends the connection, e.g. after a timeout or the `disconnect` command.
+#[derive(Event)]
+pub struct ClientDisconnected(pub String);
+
+#[derive(Resource)]
+pub struct Client {
+    socket: UdpSocket,
+    name: String,
+    id: Option<PlayerId>,
+    started: Duration,
+    last_connect: Option<Duration>,
+    last_received: Duration,
+    last_tick: u32,
+    /// Bits of the parts of the latest snapshot received so far.
+    snapshot_parts: u32,
+    /// The players listed in the parts of the latest snapshot.
+    snapshot_players: Vec<PlayerId>,
+    requested_level: Option<u8>,
+}
+
+impl Client {
+    pub fn connect(addr: SocketAddr, name: String, now: Duration) -> std::io::Result<Self> {
+        let socket = match addr {
+            SocketAddr::V4(_) => UdpSocket::bind("0.0.0.0:0")?,
+            SocketAddr::V6(_) => UdpSocket::bind("[::]:0")?,
+        };
+        socket.connect(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            name,
+            id: None,
+            started: now,
+            last_connect: None,
+            last_received: now,
+            last_tick: 0,
+            snapshot_parts: 0,
+            snapshot_players: Vec::new(),
+            requested_level: None,
+        })
+    }
+
+    pub fn id(&self) -> Option<PlayerId> {
+        self.id
+    }
+
+    /// Sends a chat message, the server relays it to everyone including us.
+    pub fn say(&self, text: &str) {
+        self.send(&ClientMessage::Chat {
+            text: sanitize_chat(text),
+        });
+    }
+
+    pub(super) fn send(&self, message: &ClientMessage) {
+        if let Err(e) = self.socket.send(&encode(message)) {
+            debug!("Could not send to the server: {e}");
+        }
+    }
+}
+
+/// A player connected to the same server, rendered from replicated state.
+#[derive(Component)]
+pub struct RemotePlayer {
+    pub id: PlayerId,
+    pub name: String,
+}
+
+/// The state a remote player is rendered with, interpolated between snapshots.
+#[derive(Component, Default, Debug)]
+pub struct ReplicatedState(pub PlayerState);
+
+/// Received states with the time they arrived at.
+#[derive(Component, Default)]
+struct RemoteStates(VecDeque<(f32, PlayerState)>);
+
+#[derive(Resource)]
+struct RemotePlayerAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup(
+    mut cmd: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    cmd.insert_resource(RemotePlayerAssets {
+        mesh: meshes.add(Capsule3d::new(1.0, PLAYER_HEIGHT - 2.0)),
+        material: materials.add(StandardMaterial {
+            base_color: Resurrect64::TURQUOISE,
+            emissive: Resurrect64::TURQUOISE.to_linear() * 2.,
+            ..default()
+        }),
+    });
+}
+
+fn client_connect(
+    mut client: ResMut<Client>,
+    time: Res<Time<Real>>,
+    mut ew: EventWriter<ClientDisconnected>,
+) {
+    if client.id.is_some() {
+        return;
+    }
+
+    let now = time.elapsed();
+
+    if now.saturating_sub(client.started) > CONNECT_TIMEOUT {
+        ew.write(ClientDisconnected(String::from("Connection timed out.")));
+        return;
+    }
+
+    if client
+        .last_connect
+        .is_some_and(|x| now.saturating_sub(x) < CONNECT_RETRY)
+    {
+        return;
+    }
+
+    client.last_connect = Some(now);
+    client.send(&ClientMessage::Connect {
+        version: PROTOCOL_VERSION,
+        name: client.name.clone(),
+    });
+}
+
+fn client_receive(
+    mut cmd: Commands,
+    mut client: ResMut<Client>,
+    mut q_remote: Query<(Entity, &RemotePlayer, &mut RemoteStates)>,
+    assets: Res<RemotePlayerAssets>,
+    time: Res<Time<Real>>,
+    mut ew: EventWriter<ClientDisconnected>,
+    mut ns_mode: ResMut<NextState<GameModeState>>,
+    app_state: Res<State<AppState>>,
+    mut ns_app: ResMut<NextState<AppState>>,
+    current_level: Res<CurrentLevel>,
+    mut ew_level: EventWriter<SpawnLevel>,
+    mut prediction: ResMut<Prediction>,
+    mut race_status: ResMut<RaceStatus>,
+    mut chat_log: ResMut<ChatLog>,
+    mut ew_invalidate: EventWriter<InvalidateRun>,
+    mut category: ResMut<RunCategory>,
+) {
+    let mut buf = [0; MAX_PACKET_SIZE];
+
+    loop {
+        let len = match client.socket.recv(&mut buf) {
+            Ok(x) => x,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+            // Nothing listens on the server port (yet), the connect retries and timeouts handle it.
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset
+                ) =>
+            {
+                continue;
+            }
+            Err(e) => {
+                ew.write(ClientDisconnected(e.to_string()));
+                return;
+            }
+        };
+
+        let Some(message) = decode::<ServerMessage>(&buf[..len]) else {
+            continue;
+        };
+
+        client.last_received = time.elapsed();
+
+        match message {
+            ServerMessage::Accepted { id, tick_rate } => {
+                if client.id.is_some() {
+                    continue;
+                }
+
+                info!("Connected as player {id}, playing at {tick_rate}.");
+
+                // Applied to the fixed timestep by the `PresetPlugin`.
+                if category.tick_rate != tick_rate {
+                    category.tick_rate = tick_rate;
+
+                    if *app_state.get() == AppState::InGame {
+                        ew_invalidate.write(InvalidateRun(Violation::CategoryChanged));
+                    }
+                }
+
+                client.id = Some(id);
+                ns_mode.set(GameModeState::Multiplayer);
+
+                if *app_state.get() != AppState::InGame {
+                    ns_app.set(AppState::InGame);
+                }
+            }
+            ServerMessage::Rejected { reason } => {
+                ew.write(ClientDisconnected(reason));
+                return;
+            }
+            ServerMessage::Snapshot {
+                tick,
+                level,
+                part,
+                parts,
+                players,
+            } => {
+                // Drop snapshots that arrive out of order, the other parts of the latest one are still taken.
+                if client.last_tick != 0 && (tick.wrapping_sub(client.last_tick) as i32) < 0 {
+                    continue;
+                }
+
+                if tick != client.last_tick {
+                    client.last_tick = tick;
+                    client.snapshot_parts = 0;
+                    client.snapshot_players.clear();
+                }
+
+                client.snapshot_parts |= 1 << (part % 32);
+                client.snapshot_players.extend(players.iter().map(|x| x.id));
+
+                // Follow the level of the server, requested again once the level changed (e.g. on restart).
+                if current_level.is_changed() {
+                    client.requested_level = None;
+                }
+
+                let next = level
+                    .filter(|x| {
+                        *app_state.get() == AppState::InGame && client.requested_level != Some(*x)
+                    })
+                    .and_then(|x| NonZeroUsize::new(x as usize))
+                    .filter(|x| *x != current_level.get() && x.get() <= LEVEL_COUNT);
+
+                if let Some(next) = next {
+                    client.requested_level = Some(next.get() as u8);
+                    ew_level.write(SpawnLevel(next));
+                }
+
+                let received = time.elapsed_secs();
+
+                // Only a complete snapshot tells who left.
+                if client.snapshot_parts.count_ones() == parts as u32 {
+                    for (entity, remote, _) in &q_remote {
+                        if !client.snapshot_players.contains(&remote.id) {
+                            cmd.entity(entity).despawn();
+                        }
+                    }
+                }
+
+                if let Some(local) = players.iter().find(|x| Some(x.id) == client.id) {
+                    prediction.acknowledge(local.tick, local.state);
+                }
+
+                for player in players.into_iter().filter(|x| Some(x.id) != client.id) {
+                    if let Some((_, _, mut states)) =
+                        q_remote.iter_mut().find(|(_, x, _)| x.id == player.id)
+                    {
+                        if states.0.len() >= REMOTE_STATE_BUFFER {
+                            states.0.pop_front();
+                        }
+                        states.0.push_back((received, player.state));
+                        continue;
+                    }
+
+                    info!("{} joined.", player.name);
+
+                    cmd.spawn((
+                        RemotePlayer {
+                            id: player.id,
+                            name: player.name,
+                        },
+                        RemoteStates(VecDeque::from([(received, player.state)])),
+                        ReplicatedState(player.state),
+                        Mesh3d(assets.mesh.clone()),
+                        MeshMaterial3d(assets.material.clone()),
+                        Transform::from_translation(player.state.translation()),
+                        Visibility::Hidden,
+                    ));
+                }
+            }
+            ServerMessage::Race(info) => {
+                race_status.receive(info, time.elapsed());
+            }
+            ServerMessage::Invalidated(deviation) => {
+                ew_invalidate.write(InvalidateRun(Violation::Movement(deviation)));
+            }
+            ServerMessage::Chat { name, text } => {
+                chat_log.push(name, text, time.elapsed());
+            }
+            ServerMessage::Disconnected { id } => {
+                for (entity, remote, _) in &q_remote {
+                    if remote.id == id {
+                        info!("{} left.", remote.name);
+                        cmd.entity(entity).despawn();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lets listen servers announce personal bests and records, only valid runs count.
+fn report_level_times(
+    mut er: EventReader<LevelFinished>,
+    q_player: Query<(), With<LogicalPlayer>>,
+    client: Res<Client>,
+    integrity: Res<RunIntegrity>,
+    current_level: Res<CurrentLevel>,
+    level_duration: Res<LevelDuration>,
+) {
+    if !er.read().any(|e| q_player.contains(e.entity))
+        || client.id.is_none()
+        || !integrity.is_valid()
+    {
+        return;
+    }
+
+    client.send(&ClientMessage::LevelTime {
+        level: current_level.get().get() as u8,
+        time: level_duration.0.elapsed_secs(),
+    });
+}
+
+fn client_timeout(
+    client: Res<Client>,
+    time: Res<Time<Real>>,
+    mut ew: EventWriter<ClientDisconnected>,
+) {
+    if client.id.is_none() {
+        return;
+    }
+
+    if time.elapsed().saturating_sub(client.last_received) > SERVER_TIMEOUT {
+        ew.write(ClientDisconnected(String::from("Server timed out.")));
+    }
+}
+
+pub(super) fn client_send(
+    client: Res<Client>,
+    prediction: Res<Prediction>,
+    current_level: Res<CurrentLevel>,
+    level_duration: Res<LevelDuration>,
+    run_duration: Res<RunDuration>,
+    category: Res<RunCategory>,
+    display: Option<Res<InputDisplay>>,
+) {
+    if client.id.is_none() || prediction.tick() % SEND_INTERVAL != 0 {
+        return;
+    }
+
+    let Some(latest) = prediction.latest() else {
+        return;
+    };
+
+    let level_time = level_duration.0.elapsed_secs();
+
+    let mut inputs = prediction
+        .unacknowledged()
+        .rev()
+        .take(REDUNDANT_INPUTS)
+        .map(|x| x.input)
+        .collect::<Vec<_>>();
+    inputs.reverse();
+
+    client.send(&ClientMessage::Update {
+        tick: latest.input.tick,
+        state: PlayerState {
+            translation: latest.translation.to_array(),
+            velocity: latest.velocity.to_array(),
+            yaw: latest.input.yaw,
+            pitch: latest.input.pitch,
+            level: current_level.get().get() as u8,
+            level_time,
+            run_time: run_duration
+                .results
+                .iter()
+                .map(|x| x.as_secs_f32())
+                .sum::<f32>()
+                + level_time,
+            category: *category,
+            keys: InputKeys::new(
+                Vec3::from_array(latest.input.movement),
+                // Scroll jumps only last a tick, any since the last update counts.
+                inputs
+                    .iter()
+                    .rev()
+                    .take(SEND_INTERVAL as usize)
+                    .any(|x| x.jump)
+                    .then(|| display.map_or_else(default, |x| x.jump_kind)),
+                latest.input.crouch,
+            ),
+        },
+        inputs,
+    });
+}
+
+fn handle_disconnect(
+    mut cmd: Commands,
+    mut er: EventReader<ClientDisconnected>,
+    client: Option<Res<Client>>,
+    q_remote: Query<Entity, With<RemotePlayer>>,
+    mode: Res<State<GameModeState>>,
+    mut ns_mode: ResMut<NextState<GameModeState>>,
+) {
+    for ClientDisconnected(reason) in er.read() {
+        info!("Disconnected: {reason}");
+    }
+
+    if let Some(client) = client {
+        client.send(&ClientMessage::Disconnect);
+    }
+
+    cmd.remove_resource::<Client>();
+    // A listen server goes down with its local client.
+    cmd.remove_resource::<Server>();
+
+    for entity in &q_remote {
+        cmd.entity(entity).despawn();
+    }
+
+    if *mode.get() == GameModeState::Multiplayer {
+        ns_mode.set(GameModeState::Singleplayer);
+    }
+}
+
+fn interpolate_remote_players(
+    mut q_remote: Query<
+        (
+            &RemoteStates,
+            &mut ReplicatedState,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<RemotePlayer>,
+    >,
+    current_level: Option<Res<CurrentLevel>>,
+    time: Res<Time<Real>>,
+) {
+    let render_time = time.elapsed_secs() - INTERPOLATION_DELAY;
+    let level = current_level.map(|x| x.get().get() as u8);
+
+    for (states, mut replicated, mut transform, mut visibility) in &mut q_remote {
+        let Some(&(_, latest)) = states.0.back() else {
+            continue;
+        };
+
+        let state = states
+            .0
+            .iter()
+            .zip(states.0.iter().skip(1))
+            .find(|((from, _), (to, _))| (*from..=*to).contains(&render_time))
+            .map(|((from, a), (to, b))| {
+                a.lerp(b, (render_time - from) / (to - from).max(f32::EPSILON))
+            })
+            .unwrap_or(latest);
+
+        transform.translation = state.translation();
+        transform.rotation = Quat::from_rotation_y(state.yaw);
+        replicated.0 = state;
+
+        *visibility = match Some(state.level) == level {
+            true => Visibility::Visible,
+            false => Visibility::Hidden,
+        };
+    }
+}
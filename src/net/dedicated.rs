@@ -0,0 +1,543 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_fps_controller::controller::*;
+use serde::Deserialize;
+
+use crate::{core::*, player::*, preset::*, style::*, world::*};
+
+use super::*;
+
+/// Settings of a dedicated server, read from a RON file and overridden from the command line.
+#[derive(Resource, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub max_players: usize,
+    /// Levels played in order, starting over after the last one.
+    pub maps: Vec<usize>,
+    /// Race every map instead of moving on once everyone finished it.
+    pub race: bool,
+    /// Seconds of countdown before a race starts.
+    pub countdown: f32,
+    /// Seconds players spend in the lobby between races.
+    pub lobby_time: f32,
+    /// Every player is simulated at this rate, clients switch to it when they join.
+    pub tick_rate: TickRate,
+    pub validation: ValidationConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            max_players: DEFAULT_MAX_PLAYERS,
+            maps: (1..=LEVEL_COUNT).collect(),
+            race: false,
+            countdown: DEFAULT_COUNTDOWN,
+            lobby_time: 30.,
+            tick_rate: default(),
+            validation: default(),
+        }
+    }
+}
+
+/// Runs the gameplay of the current map for every connected player, without any presentation.
+pub struct DedicatedServerPlugin(pub ServerConfig);
+
+impl Plugin for DedicatedServerPlugin {
+    fn build(&self, app: &mut App) {
+        let maps = self
+            .0
+            .maps
+            .iter()
+            .filter_map(|x| NonZeroUsize::new(*x).filter(|x| x.get() <= LEVEL_COUNT))
+            .collect::<Vec<_>>();
+
+        if maps.len() != self.0.maps.len() {
+            warn!("Ignoring maps outside of 1..={LEVEL_COUNT}.");
+        }
+
+        app.add_plugins((ServerPlugin, MovementPlugin))
+            .insert_resource(self.0.clone())
+            .insert_resource(MapRotation {
+                maps: match maps.is_empty() {
+                    true => vec![NonZeroUsize::MIN],
+                    false => maps,
+                },
+                current: 0,
+            })
+            .add_systems(Startup, bind)
+            .add_systems(Update, finish_loading.run_if(in_state(AppState::Loading)))
+            .add_systems(
+                Update,
+                (
+                    spawn_net_players,
+                    despawn_net_players,
+                    track_progress,
+                    rotate_maps,
+                    start_races,
+                    sync_server_level,
+                    reset_validation.run_if(on_event::<LevelSpawned>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame).and(resource_exists::<Server>)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    simulate_net_players.before(MovementSet),
+                    freeze_net_players.after(MovementSet),
+                )
+                    .in_set(GameplaySet)
+                    .run_if(resource_exists::<Server>),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct MapRotation {
+    maps: Vec<NonZeroUsize>,
+    current: usize,
+}
+
+impl MapRotation {
+    pub fn current(&self) -> NonZeroUsize {
+        self.maps[self.current]
+    }
+
+    fn advance(&mut self) -> NonZeroUsize {
+        self.current = (self.current + 1) % self.maps.len();
+        self.current()
+    }
+}
+
+/// A connected player simulated on the server.
+#[derive(Component, Debug)]
+pub struct NetPlayer {
+    pub id: PlayerId,
+    pub name: String,
+}
+
+/// Progress of a player on the current map, timed by the server.
+#[derive(Component, Debug, Default)]
+pub struct MapProgress {
+    pub started: Duration,
+    /// The checkpoints the server saw the player touch, the only ones it may respawn at.
+    pub checkpoints: Vec<Entity>,
+    pub finished: Option<Duration>,
+}
+
+fn bind(
+    mut cmd: Commands,
+    config: Res<ServerConfig>,
+    mut time: ResMut<Time<Fixed>>,
+    mut ew: EventWriter<AppExit>,
+) {
+    match Server::bind(config.port, config.max_players) {
+        Ok(mut server) => {
+            server.authoritative = true;
+            server.validation = config.validation;
+            server.tick_rate = config.tick_rate;
+            time.set_timestep(config.tick_rate.timestep());
+
+            info!(
+                "Listening on {} for up to {} players at {}.",
+                server
+                    .local_addr()
+                    .map_or_else(|_| config.port.to_string(), |x| x.to_string()),
+                config.max_players,
+                config.tick_rate
+            );
+            cmd.insert_resource(server);
+        }
+        Err(e) => {
+            error!("Could not bind port {}: {e}", config.port);
+            ew.write(AppExit::error());
+        }
+    }
+}
+
+/// Stands in for the loading screen, the server goes straight into the first map.
+fn finish_loading(
+    mut cmd: Commands,
+    scene: Option<Res<MainScene>>,
+    mut current_level: Option<ResMut<CurrentLevel>>,
+    rotation: Res<MapRotation>,
+    server: Res<AssetServer>,
+    loading: Res<AssetsLoading>,
+    mut ns_app: ResMut<NextState<AppState>>,
+    mut ns_mode: ResMut<NextState<GameModeState>>,
+) {
+    let (Some(_), Some(current_level)) = (scene, current_level.as_mut()) else {
+        return;
+    };
+
+    if loading.get(server) {
+        return;
+    }
+
+    cmd.remove_resource::<AssetsLoading>();
+    current_level.0 = rotation.current();
+
+    info!("Loaded, starting with level {}.", rotation.current());
+
+    ns_app.set(AppState::InGame);
+    ns_mode.set(GameModeState::Multiplayer);
+}
+
+fn spawn_net_players(mut cmd: Commands, mut er: EventReader<PlayerJoined>, time: Res<Time>) {
+    for PlayerJoined { id, name } in er.read() {
+        cmd.spawn((
+            NetPlayer {
+                id: *id,
+                name: name.clone(),
+            },
+            MapProgress {
+                started: time.elapsed(),
+                ..default()
+            },
+            player_body(),
+            Transform::from_translation(SPAWN_POINT),
+            // Driven by the inputs the client sends, see `simulate_net_players`.
+            FpsController {
+                enable_input: false,
+                ..default()
+            },
+            FpsControllerInput::default(),
+            Style::default(),
+            MovementPreset::default(),
+        ));
+    }
+}
+
+fn despawn_net_players(
+    mut cmd: Commands,
+    mut er: EventReader<PlayerLeft>,
+    q_players: Query<(Entity, &NetPlayer)>,
+) {
+    for PlayerLeft { id } in er.read() {
+        for (entity, _) in q_players.iter().filter(|(_, x)| x.id == *id) {
+            cmd.entity(entity).despawn();
+        }
+    }
+}
+
+/// Feeds the next input of every player to its controller, players on another level don't collide.
+fn simulate_net_players(
+    mut cmd: Commands,
+    mut server: ResMut<Server>,
+    current_level: Res<CurrentLevel>,
+    mut q_players: Query<(
+        Entity,
+        &NetPlayer,
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut FpsController,
+        &mut FpsControllerInput,
+        &mut Style,
+        &mut MovementPreset,
+        &mut MapProgress,
+        Has<ColliderDisabled>,
+    )>,
+    q_checkpoints: Query<&GlobalTransform, With<CheckPoint>>,
+    time: Res<Time<Virtual>>,
+) {
+    for (
+        entity,
+        player,
+        mut transform,
+        mut velocity,
+        mut controller,
+        mut input,
+        mut style,
+        mut preset,
+        mut progress,
+        disabled,
+    ) in &mut q_players
+    {
+        let Some(state) = server.player(player.id).map(|(_, x)| *x) else {
+            continue;
+        };
+
+        let on_level = state.level as usize == current_level.get().get();
+
+        // Applied by `apply_style` in the `MovementSet`, like on the client.
+        style.set_if_neq(state.category.style);
+        preset.set_if_neq(state.category.preset);
+
+        match (on_level, disabled) {
+            (true, true) => {
+                cmd.entity(entity).remove::<ColliderDisabled>();
+            }
+            (false, false) => {
+                cmd.entity(entity).insert(ColliderDisabled);
+            }
+            _ => {}
+        }
+
+        // Without a new input the last one is held, like a key that is still pressed.
+        let Some(frame) = server.next_input(player.id) else {
+            continue;
+        };
+
+        if let Some(teleport) = frame.teleport {
+            let respawn_points = std::iter::once(SPAWN_POINT).chain(
+                q_checkpoints
+                    .iter_many(&progress.checkpoints)
+                    .map(respawn_point),
+            );
+
+            match take_teleport(
+                &server.validation,
+                &teleport,
+                respawn_points,
+                &mut transform,
+                &mut velocity,
+            ) {
+                true if restarts(&server.validation, &teleport) => {
+                    *progress = MapProgress {
+                        started: time.elapsed(),
+                        ..default()
+                    };
+                }
+                true => {}
+                false => server.reject(player.id, Deviation::Teleport),
+            }
+        }
+
+        // The state the input is applied to, which the client compares with its prediction.
+        server.acknowledge(player.id, frame.tick, transform.translation, velocity.0);
+
+        apply_input_frame(&frame, &mut input);
+
+        // Noclip is a cheat, the server keeps simulating the player on the ground.
+        if frame.noclip {
+            server.reject(player.id, Deviation::Noclip);
+        }
+
+        controller.move_mode = MoveMode::Ground;
+    }
+}
+
+/// Moves the player to the teleport if it is allowed, otherwise the server keeps simulating it where it was.
+fn take_teleport(
+    validation: &ValidationConfig,
+    teleport: &Teleport,
+    respawn_points: impl Iterator<Item = Vec3>,
+    transform: &mut Transform,
+    velocity: &mut LinearVelocity,
+) -> bool {
+    if !validation.allows_teleport(teleport, respawn_points) {
+        return false;
+    }
+
+    transform.translation = Vec3::from_array(teleport.translation);
+    velocity.0 = Vec3::from_array(teleport.velocity);
+    true
+}
+
+/// A respawn on the spawn starts the level over, the time of the failed attempts doesn't count.
+fn restarts(validation: &ValidationConfig, teleport: &Teleport) -> bool {
+    Vec3::from_array(teleport.translation).distance(SPAWN_POINT) <= validation.teleport_distance
+}
+
+fn track_progress(
+    mut er_checkpoint: EventReader<CheckpointReached>,
+    mut er_finished: EventReader<LevelFinished>,
+    mut q_players: Query<(&NetPlayer, &mut MapProgress)>,
+    mut server: ResMut<Server>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    for e in er_checkpoint.read() {
+        let Ok((_, mut progress)) = q_players.get_mut(e.entity) else {
+            continue;
+        };
+
+        if !progress.checkpoints.contains(&e.checkpoint) {
+            progress.checkpoints.push(e.checkpoint);
+        }
+    }
+
+    for e in er_finished.read() {
+        let Ok((player, mut progress)) = q_players.get_mut(e.entity) else {
+            continue;
+        };
+
+        if progress.finished.is_some() {
+            continue;
+        }
+
+        let duration = time.elapsed().saturating_sub(progress.started);
+        progress.finished = Some(duration);
+
+        info!(
+            "{} finished level {} in {:.3}s ({} checkpoints).",
+            player.name,
+            current_level.get(),
+            duration.as_secs_f32(),
+            progress.checkpoints.len()
+        );
+
+        server.record_time(
+            player.id,
+            current_level.get().get() as u8,
+            duration.as_secs_f32(),
+        );
+
+        // Races are timed in server ticks, the same for everyone.
+        if let Some(time) = server
+            .race
+            .as_ref()
+            .map(|x| x.elapsed(server.tick(), fixed_time.timestep()))
+        {
+            server.finish(player.id, time);
+        }
+    }
+}
+
+fn reset_validation(mut server: ResMut<Server>) {
+    server.reset_validation();
+}
+
+/// Moves on to the next map once every connected player finished the current one.
+fn rotate_maps(
+    mut q_players: Query<&mut MapProgress>,
+    mut rotation: ResMut<MapRotation>,
+    mut ew: EventWriter<SpawnLevel>,
+    time: Res<Time>,
+    config: Res<ServerConfig>,
+) {
+    if config.race || q_players.is_empty() || q_players.iter().any(|x| x.finished.is_none()) {
+        return;
+    }
+
+    let next = rotation.advance();
+
+    info!("Everyone finished, changing to level {next}.");
+
+    ew.write(SpawnLevel(next));
+
+    for mut progress in &mut q_players {
+        *progress = MapProgress {
+            started: time.elapsed(),
+            ..default()
+        };
+    }
+}
+
+fn sync_server_level(mut server: ResMut<Server>, current_level: Res<CurrentLevel>) {
+    let level = Some(current_level.get().get() as u8);
+
+    if server.level != level {
+        server.level = level;
+    }
+}
+
+/// In race mode, starts a race on the next map once the players spent some time in the lobby.
+fn start_races(
+    mut server: ResMut<Server>,
+    mut rotation: ResMut<MapRotation>,
+    mut q_progress: Query<&mut MapProgress>,
+    mut ew: EventWriter<SpawnLevel>,
+    config: Res<ServerConfig>,
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+    mut lobby_since: Local<Option<Duration>>,
+) {
+    if !config.race || server.race.is_some() || server.player_count() == 0 {
+        *lobby_since = None;
+        return;
+    }
+
+    let since = *lobby_since.get_or_insert(time.elapsed());
+
+    if time.elapsed().saturating_sub(since).as_secs_f32() < config.lobby_time {
+        return;
+    }
+
+    *lobby_since = None;
+
+    let next = rotation.advance();
+
+    ew.write(SpawnLevel(next));
+
+    server.start_race(
+        next.get() as u8,
+        Duration::from_secs_f32(config.countdown.max(0.)),
+        fixed_time.timestep(),
+    );
+
+    for mut progress in &mut q_progress {
+        *progress = MapProgress {
+            started: time.elapsed(),
+            ..default()
+        };
+    }
+}
+
+/// Holds every player on the spawn during the countdown, like the clients do.
+fn freeze_net_players(
+    server: Res<Server>,
+    mut q_players: Query<(&mut Transform, &mut LinearVelocity), With<NetPlayer>>,
+) {
+    if server
+        .race
+        .as_ref()
+        .is_none_or(|x| x.is_running(server.tick()))
+    {
+        return;
+    }
+
+    for (mut transform, mut velocity) in &mut q_players {
+        transform.translation = SPAWN_POINT;
+        velocity.0 = Vec3::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn teleports() {
+        let validation = ValidationConfig::default();
+        let mut transform = Transform::from_translation(Vec3::X);
+        let mut velocity = LinearVelocity(Vec3::Z);
+
+        let teleport = Teleport {
+            translation: (SPAWN_POINT + Vec3::X * 100.).to_array(),
+            velocity: [0.; 3],
+        };
+
+        assert!(!take_teleport(
+            &validation,
+            &teleport,
+            std::iter::once(SPAWN_POINT),
+            &mut transform,
+            &mut velocity
+        ));
+        assert_eq!(transform.translation, Vec3::X);
+        assert_eq!(velocity.0, Vec3::Z);
+        assert!(!restarts(&validation, &teleport));
+
+        let teleport = Teleport {
+            translation: SPAWN_POINT.to_array(),
+            velocity: [0.; 3],
+        };
+
+        assert!(take_teleport(
+            &validation,
+            &teleport,
+            std::iter::once(SPAWN_POINT),
+            &mut transform,
+            &mut velocity
+        ));
+        assert_eq!(transform.translation, SPAWN_POINT);
+        assert_eq!(velocity.0, Vec3::ZERO);
+        assert!(restarts(&validation, &teleport));
+    }
+}
@@ -0,0 +1,163 @@
+mod client;
+mod dedicated;
+mod prediction;
+mod protocol;
+mod race;
+mod server;
+mod validation;
+
+pub use client::*;
+pub use dedicated::*;
+pub use prediction::*;
+pub use protocol::*;
+pub use race::*;
+pub use server::*;
+pub use validation::*;
+
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use bevy::prelude::*;
+use bevy_console::*;
+use clap::Parser;
+
+use crate::records::RunCategory;
+
+pub const DEFAULT_PORT: u16 = 27015;
+pub const DEFAULT_MAX_PLAYERS: usize = 16;
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((ServerPlugin, ClientPlugin, PredictionPlugin, RacePlugin))
+            .add_console_command::<HostCommand, _>(host)
+            .add_console_command::<ConnectCommand, _>(connect)
+            .add_console_command::<DisconnectCommand, _>(disconnect);
+    }
+}
+
+/// Resolves `host[:port]`, falling back to the default port.
+pub fn resolve(address: &str) -> std::io::Result<SocketAddr> {
+    let address = match address.contains(':') {
+        true => address.to_string(),
+        false => format!("{address}:{DEFAULT_PORT}"),
+    };
+
+    address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Address didn't resolve"))
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "host")]
+struct HostCommand {
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+    #[arg(long, default_value_t = DEFAULT_MAX_PLAYERS)]
+    max_players: usize,
+    #[arg(long, default_value = "player")]
+    name: String,
+}
+
+fn host(
+    mut log: ConsoleCommand<HostCommand>,
+    mut cmd: Commands,
+    server: Option<Res<Server>>,
+    client: Option<Res<Client>>,
+    time: Res<Time<Real>>,
+    category: Res<RunCategory>,
+) {
+    let Some(Ok(HostCommand {
+        port,
+        max_players,
+        name,
+    })) = log.take()
+    else {
+        return;
+    };
+
+    if server.is_some() || client.is_some() {
+        reply!(log, "Already connected, disconnect first.");
+        return;
+    }
+
+    let mut server = match Server::bind(port, max_players) {
+        Ok(x) => x,
+        Err(e) => {
+            reply!(log, "Could not host on port {port}: {e}");
+            return;
+        }
+    };
+
+    // The listen server runs in the fixed timestep of the host.
+    server.tick_rate = category.tick_rate;
+
+    match Client::connect(
+        SocketAddr::from(([127, 0, 0, 1], port)),
+        name,
+        time.elapsed(),
+    ) {
+        Ok(client) => {
+            reply!(log, "Hosting on port {port}");
+            cmd.insert_resource(server);
+            cmd.insert_resource(client);
+        }
+        Err(e) => reply!(log, "Could not connect to the local server: {e}"),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "connect")]
+struct ConnectCommand {
+    /// Server address, host[:port]
+    #[arg(index = 1)]
+    address: String,
+    #[arg(long, default_value = "player")]
+    name: String,
+}
+
+fn connect(
+    mut log: ConsoleCommand<ConnectCommand>,
+    mut cmd: Commands,
+    client: Option<Res<Client>>,
+    time: Res<Time<Real>>,
+) {
+    let Some(Ok(ConnectCommand { address, name })) = log.take() else {
+        return;
+    };
+
+    if client.is_some() {
+        reply!(log, "Already connected, disconnect first.");
+        return;
+    }
+
+    match resolve(&address).and_then(|addr| Client::connect(addr, name, time.elapsed())) {
+        Ok(client) => {
+            reply!(log, "Connecting to {address}...");
+            cmd.insert_resource(client);
+        }
+        Err(e) => reply!(log, "Could not connect to {address}: {e}"),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "disconnect")]
+struct DisconnectCommand {}
+
+fn disconnect(
+    mut log: ConsoleCommand<DisconnectCommand>,
+    client: Option<Res<Client>>,
+    mut ew: EventWriter<ClientDisconnected>,
+) {
+    let Some(Ok(DisconnectCommand {})) = log.take() else {
+        return;
+    };
+
+    if client.is_none() {
+        reply!(log, "Not connected.");
+        return;
+    }
+
+    ew.write(ClientDisconnected(String::from("Disconnected by user.")));
+}
@@ -0,0 +1,429 @@
+use std::collections::VecDeque;
+
+use avian3d::prelude::*;
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, transform::TransformSystem};
+use bevy_fps_controller::controller::*;
+
+use crate::{core::*, player::MovementSet, style::apply_style};
+
+use super::*;
+
+/// Predicted ticks kept around for reconciliation, 1s at the default 128 Hz tickrate.
+const PREDICTION_HISTORY: usize = 128;
+
+/// Smaller differences to the server are rounding, not mispredictions.
+const MIN_ERROR: f32 = 0.01;
+
+/// Corrections larger than this are snapped to instead of smoothed.
+const SNAP_DISTANCE: f32 = 5.;
+
+/// Time constant of the camera catching up with a correction.
+const SMOOTHING: f32 = 0.1;
+
+/// Moving this far off what the velocity allows within a tick is a teleport, e.g. a respawn.
+const TELEPORT_DISTANCE: f32 = 4.;
+
+/// Surfaces a replayed tick slides along before it stops.
+const MAX_SLIDES: usize = 4;
+
+/// Replayed moves stop this short of what they hit, so the next cast doesn't start inside it.
+/// Well below `MIN_ERROR`, the physics doesn't keep a gap.
+const SKIN: f32 = 0.001;
+
+/// Predicts the local player from its own inputs and reconciles it with the server.
+pub struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Prediction>()
+            .add_systems(Update, reset_prediction.run_if(resource_added::<Client>))
+            .add_systems(
+                FixedUpdate,
+                (reconcile, record_prediction)
+                    .chain()
+                    .before(client_send)
+                    .before(MovementSet)
+                    .in_set(MultiplayerSet)
+                    .run_if(resource_exists::<Client>),
+            )
+            .add_systems(
+                ReplayTick,
+                (
+                    apply_style,
+                    fps_controller_look,
+                    fps_controller_move,
+                    replay_physics,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                smooth_corrections
+                    .after(fps_controller_render)
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(resource_exists::<Client>),
+            );
+    }
+}
+
+/// A single tick of the local player, run by `reconcile` for every input the server didn't simulate yet.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct ReplayTick;
+
+/// A tick of the local player, with the state its input was applied to.
+#[derive(Debug, Clone, Copy)]
+pub struct PredictedTick {
+    pub input: InputFrame,
+    pub translation: Vec3,
+    pub velocity: Vec3,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Prediction {
+    tick: u32,
+    history: VecDeque<PredictedTick>,
+    /// The latest server state of the local player and the tick it reflects.
+    acknowledged: Option<(u32, PlayerState)>,
+    /// Offset of the camera from the corrected player, decaying towards zero.
+    error: Vec3,
+}
+
+impl Prediction {
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn latest(&self) -> Option<&PredictedTick> {
+        self.history.back()
+    }
+
+    /// The ticks that aren't settled by the server yet, oldest first.
+    pub fn unacknowledged(&self) -> impl DoubleEndedIterator<Item = &PredictedTick> {
+        self.history.iter()
+    }
+
+    pub(super) fn acknowledge(&mut self, tick: u32, state: PlayerState) {
+        self.acknowledged = Some((tick, state));
+    }
+}
+
+fn reset_prediction(mut prediction: ResMut<Prediction>) {
+    *prediction = default();
+}
+
+/// Feeds an input the client recorded into the controller, as it was when the client simulated it.
+pub(super) fn apply_input_frame(frame: &InputFrame, input: &mut FpsControllerInput) {
+    input.movement = Vec3::from_array(frame.movement);
+    input.jump = frame.jump;
+    input.crouch = frame.crouch;
+    input.sprint = frame.sprint;
+    input.yaw = frame.yaw;
+    input.pitch = frame.pitch;
+}
+
+/// Rewinds to the acknowledged server state and replays the unacknowledged inputs on top of it.
+fn reconcile(world: &mut World) {
+    let Some((tick, state)) = world.resource_mut::<Prediction>().acknowledged.take() else {
+        return;
+    };
+
+    let mut prediction = world.resource_mut::<Prediction>();
+
+    // Ticks before the acknowledged one are settled.
+    while prediction
+        .history
+        .front()
+        .is_some_and(|x| tick.wrapping_sub(x.input.tick) as i32 > 0)
+    {
+        prediction.history.pop_front();
+    }
+
+    let Some(predicted) = prediction
+        .history
+        .front()
+        .filter(|x| x.input.tick == tick)
+        .copied()
+    else {
+        return;
+    };
+
+    if state.translation().distance(predicted.translation) < MIN_ERROR
+        && state.velocity().distance(predicted.velocity) < MIN_ERROR
+    {
+        return;
+    }
+
+    let frames = prediction
+        .history
+        .iter()
+        .map(|x| x.input)
+        .collect::<Vec<_>>();
+
+    let mut q_player = world.query_filtered::<(
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut FpsController,
+        &mut FpsControllerInput,
+    ), With<LogicalPlayer>>();
+
+    let Ok((mut transform, mut velocity, controller, input)) = q_player.single_mut(world) else {
+        return;
+    };
+
+    let previous = transform.translation;
+    // The input of this frame, the replay goes through the recorded ones.
+    let live = InputFrame {
+        movement: input.movement.to_array(),
+        jump: input.jump,
+        crouch: input.crouch,
+        sprint: input.sprint,
+        noclip: matches!(controller.move_mode, MoveMode::Noclip),
+        yaw: input.yaw,
+        pitch: input.pitch,
+        ..default()
+    };
+
+    transform.translation = state.translation();
+    velocity.0 = state.velocity();
+
+    for (i, frame) in frames.iter().enumerate() {
+        let Ok((transform, velocity, mut controller, mut input)) = q_player.single_mut(world)
+        else {
+            break;
+        };
+
+        let (translation, velocity) = (transform.translation, velocity.0);
+
+        apply_input_frame(frame, &mut input);
+        controller.move_mode = match frame.noclip {
+            true => MoveMode::Noclip,
+            false => MoveMode::Ground,
+        };
+
+        if let Some(x) = world.resource_mut::<Prediction>().history.get_mut(i) {
+            x.translation = translation;
+            x.velocity = velocity;
+        }
+
+        world.run_schedule(ReplayTick);
+    }
+
+    let Ok((transform, _, mut controller, mut input)) = q_player.single_mut(world) else {
+        return;
+    };
+
+    apply_input_frame(&live, &mut input);
+    controller.move_mode = match live.noclip {
+        true => MoveMode::Noclip,
+        false => MoveMode::Ground,
+    };
+
+    let offset = transform.translation - previous;
+    let mut prediction = world.resource_mut::<Prediction>();
+
+    prediction.error = match offset.length() > SNAP_DISTANCE {
+        true => Vec3::ZERO,
+        false => prediction.error - offset,
+    };
+}
+
+/// Moves the player by its velocity for a tick, sliding along what it hits like the physics does.
+fn replay_physics(
+    spatial_query: SpatialQuery,
+    mut q_player: Query<
+        (Entity, &Collider, &mut Transform, &mut LinearVelocity),
+        With<LogicalPlayer>,
+    >,
+    time: Res<Time>,
+) {
+    let Ok((player, collider, mut transform, mut velocity)) = q_player.single_mut() else {
+        return;
+    };
+
+    let filter =
+        SpatialQueryFilter::from_mask(CollisionLayer::Default).with_excluded_entities([player]);
+    let mut remaining = velocity.0 * time.delta_secs();
+
+    for _ in 0..MAX_SLIDES {
+        let Ok(direction) = Dir3::new(remaining) else {
+            break;
+        };
+
+        let Some(hit) = spatial_query.cast_shape(
+            collider,
+            transform.translation,
+            transform.rotation,
+            direction,
+            &ShapeCastConfig::from_max_distance(remaining.length()),
+            &filter,
+        ) else {
+            transform.translation += remaining;
+            break;
+        };
+
+        transform.translation += direction * (hit.distance - SKIN).max(0.);
+        remaining =
+            (direction * (remaining.length() - hit.distance)).reject_from_normalized(hit.normal1);
+
+        if velocity.0.dot(hit.normal1) < 0. {
+            velocity.0 = velocity.0.reject_from_normalized(hit.normal1);
+        }
+    }
+}
+
+fn record_prediction(
+    mut prediction: ResMut<Prediction>,
+    q_player: Query<
+        (
+            &Transform,
+            &LinearVelocity,
+            &FpsController,
+            &FpsControllerInput,
+        ),
+        With<LogicalPlayer>,
+    >,
+    time: Res<Time>,
+) {
+    let Ok((transform, velocity, controller, input)) = q_player.single() else {
+        return;
+    };
+
+    prediction.tick = prediction.tick.wrapping_add(1);
+
+    // The first tick teleports as well, the server has to pick up wherever the player already is.
+    let teleported = prediction.history.back().is_none_or(|x| {
+        (x.translation + x.velocity * time.delta_secs()).distance(transform.translation)
+            > TELEPORT_DISTANCE
+    });
+
+    if prediction.history.len() >= PREDICTION_HISTORY {
+        prediction.history.pop_front();
+    }
+
+    let tick = PredictedTick {
+        input: InputFrame {
+            tick: prediction.tick,
+            movement: input.movement.to_array(),
+            jump: input.jump,
+            crouch: input.crouch,
+            sprint: input.sprint,
+            noclip: matches!(controller.move_mode, MoveMode::Noclip),
+            yaw: input.yaw,
+            pitch: input.pitch,
+            teleport: teleported.then(|| Teleport {
+                translation: transform.translation.to_array(),
+                velocity: velocity.0.to_array(),
+            }),
+        },
+        translation: transform.translation,
+        velocity: velocity.0,
+    };
+
+    prediction.history.push_back(tick);
+}
+
+/// Offsets the camera by the remaining error, so corrections ease in instead of snapping.
+fn smooth_corrections(
+    mut prediction: ResMut<Prediction>,
+    mut q_render: Query<&mut Transform, With<RenderPlayer>>,
+    time: Res<Time>,
+) {
+    if prediction.error == Vec3::ZERO {
+        return;
+    }
+
+    prediction.error *= (-time.delta_secs() / SMOOTHING).exp();
+
+    if prediction.error.length() < MIN_ERROR {
+        prediction.error = Vec3::ZERO;
+    }
+
+    for mut transform in &mut q_render {
+        transform.translation += prediction.error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{ecs::system::RunSystemOnce, scene::ScenePlugin, time::TimeUpdateStrategy};
+
+    use super::*;
+    use crate::player::player_body;
+
+    const TIMESTEP: Duration = Duration::from_nanos(7_812_500);
+
+    /// A player next to a wall, with the physics stepped once so the wall can be hit by casts.
+    fn wall_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            AssetPlugin::default(),
+            ScenePlugin,
+            PhysicsPlugins::default(),
+        ))
+        .init_resource::<Assets<Mesh>>()
+        .insert_resource(Time::<Fixed>::from_duration(TIMESTEP))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(TIMESTEP));
+
+        app.world_mut().spawn((
+            RigidBody::Static,
+            Collider::cuboid(1., 10., 100.),
+            CollisionLayers::new(CollisionLayer::Default, LayerMask::ALL),
+            Transform::from_xyz(3.5, 0., 0.),
+        ));
+
+        let player = app
+            .world_mut()
+            .spawn((player_body(), LogicalPlayer, Transform::default()))
+            .id();
+
+        app.finish();
+        app.cleanup();
+
+        // The first update only starts the clock.
+        app.update();
+        app.update();
+
+        (app, player)
+    }
+
+    #[test]
+    fn replay_agrees_with_physics() {
+        let (mut app, player) = wall_app();
+        let start = app.world().get::<Position>(player).unwrap().0;
+        // Diagonally into the wall for 0.2s, then along it.
+        let velocity = Vec3::new(10., 0., 5.);
+        let ticks = 64;
+
+        let world = app.world_mut();
+        for _ in 0..ticks {
+            world.get_mut::<LinearVelocity>(player).unwrap().0 = velocity;
+            world.resource_mut::<Time>().advance_by(TIMESTEP);
+            world.run_system_once(replay_physics).unwrap();
+        }
+        let replayed = world.get::<Transform>(player).unwrap().translation;
+
+        world.get_mut::<Transform>(player).unwrap().translation = start;
+        world.get_mut::<Position>(player).unwrap().0 = start;
+
+        for _ in 0..ticks {
+            app.world_mut().get_mut::<LinearVelocity>(player).unwrap().0 = velocity;
+            app.update();
+        }
+        let simulated = app.world().get::<Position>(player).unwrap().0;
+
+        // The wall stopped the player and it kept sliding along it.
+        assert!((simulated.x - 2.).abs() < MIN_ERROR, "{simulated}");
+        assert!(
+            (simulated.z - start.z - 2.5).abs() < MIN_ERROR,
+            "{simulated}"
+        );
+        assert!(
+            replayed.distance(simulated) < MIN_ERROR,
+            "{replayed} != {simulated}"
+        );
+    }
+}
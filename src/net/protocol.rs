@@ -0,0 +1,510 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{preset::TickRate, records::RunCategory};
+
+/// Bumped whenever a message changes, clients with a different version are rejected.
+pub const PROTOCOL_VERSION: u16 = 14;
+
+/// Below the usual MTU, every message has to fit.
+pub const MAX_PACKET_SIZE: usize = 1200;
+
+/// Longer chat messages are cut off.
+pub const MAX_CHAT_LENGTH: usize = 120;
+
+/// Longer names are cut off, in bytes so every player takes up a bounded part of a packet.
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// Servers don't take more players, the race results of all of them have to fit into a packet.
+pub const MAX_PLAYERS: usize = 32;
+
+/// Players per snapshot packet, the snapshot of a fuller server is split into several.
+pub const SNAPSHOT_PLAYERS: usize = 8;
+
+/// Given to players without a usable name.
+const DEFAULT_NAME: &str = "player";
+
+pub type PlayerId = u32;
+
+/// What every player replicates about itself.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerState {
+    pub translation: [f32; 3],
+    pub velocity: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub level: u8,
+    pub level_time: f32,
+    pub run_time: f32,
+    /// Times only compare within the same category, and the server filters inputs by its style.
+    pub category: RunCategory,
+    pub keys: InputKeys,
+}
+
+/// The movement keys a player holds, for input displays of spectators.
+///
+/// Replicated as a single byte, it is part of every snapshot entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(from = "u8", into = "u8")]
+pub struct InputKeys {
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub crouch: bool,
+    pub jump: Option<JumpKind>,
+}
+
+/// How a jump was pressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum JumpKind {
+    #[default]
+    Key,
+    Scroll,
+    /// Held by the `SHIFT`+`SPACE` auto-jump.
+    Auto,
+}
+
+impl InputKeys {
+    /// From the movement of the controller input, x strafing right and z forward.
+    pub fn new(movement: Vec3, jump: Option<JumpKind>, crouch: bool) -> Self {
+        Self {
+            forward: movement.z > 0.,
+            back: movement.z < 0.,
+            left: movement.x < 0.,
+            right: movement.x > 0.,
+            crouch,
+            jump,
+        }
+    }
+}
+
+impl From<InputKeys> for u8 {
+    fn from(keys: InputKeys) -> Self {
+        let jump = match keys.jump {
+            None => 0,
+            Some(JumpKind::Key) => 1,
+            Some(JumpKind::Scroll) => 2,
+            Some(JumpKind::Auto) => 3,
+        };
+
+        keys.forward as u8
+            | (keys.back as u8) << 1
+            | (keys.left as u8) << 2
+            | (keys.right as u8) << 3
+            | (keys.crouch as u8) << 4
+            | jump << 5
+    }
+}
+
+impl From<u8> for InputKeys {
+    fn from(bits: u8) -> Self {
+        Self {
+            forward: bits & 1 != 0,
+            back: bits & 1 << 1 != 0,
+            left: bits & 1 << 2 != 0,
+            right: bits & 1 << 3 != 0,
+            crouch: bits & 1 << 4 != 0,
+            jump: match bits >> 5 & 0b11 {
+                0 => None,
+                1 => Some(JumpKind::Key),
+                2 => Some(JumpKind::Scroll),
+                _ => Some(JumpKind::Auto),
+            },
+        }
+    }
+}
+
+impl PlayerState {
+    /// Reported states with NaN or infinite values are dropped.
+    pub fn is_finite(&self) -> bool {
+        self.translation().is_finite()
+            && self.velocity().is_finite()
+            && [self.yaw, self.pitch, self.level_time, self.run_time]
+                .iter()
+                .all(|x| x.is_finite())
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        Vec3::from_array(self.translation)
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        Vec3::from_array(self.velocity)
+    }
+
+    /// Linear interpolation for rendering remote players between two snapshots.
+    /// The yaw turns the short way, it isn't wrapped and can be a full turn off.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let turn = (other.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+
+        Self {
+            translation: self.translation().lerp(other.translation(), t).to_array(),
+            velocity: self.velocity().lerp(other.velocity(), t).to_array(),
+            yaw: self.yaw + turn * t,
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+            ..*other
+        }
+    }
+}
+
+/// The input of a single client tick, simulated by servers that are authoritative over movement.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputFrame {
+    pub tick: u32,
+    pub movement: [f32; 3],
+    pub jump: bool,
+    pub crouch: bool,
+    pub sprint: bool,
+    pub noclip: bool,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Set when the client moved without input (e.g. a respawn), the server takes it over.
+    pub teleport: Option<Teleport>,
+}
+
+impl InputFrame {
+    /// Inputs with NaN or infinite values never reach the simulation.
+    pub fn is_finite(&self) -> bool {
+        Vec3::from_array(self.movement).is_finite()
+            && self.yaw.is_finite()
+            && self.pitch.is_finite()
+            && self.teleport.is_none_or(|x| {
+                Vec3::from_array(x.translation).is_finite()
+                    && Vec3::from_array(x.velocity).is_finite()
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Teleport {
+    pub translation: [f32; 3],
+    pub velocity: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoteState {
+    pub id: PlayerId,
+    pub name: String,
+    /// The last client tick reflected in `state`.
+    pub tick: u32,
+    pub state: PlayerState,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RaceFinish {
+    pub id: PlayerId,
+    pub name: String,
+    /// Seconds since the start.
+    pub time: f32,
+}
+
+/// A race as replicated to the clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RaceInfo {
+    pub level: u8,
+    /// Seconds until the start, zero once the race is running.
+    pub countdown: f32,
+    /// In finishing order.
+    pub finished: Vec<RaceFinish>,
+}
+
+/// How the movement a client reported deviated from the server's simulation of its inputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deviation {
+    Teleport,
+    Speed,
+    Gravity,
+    Noclip,
+    /// Ticking faster than the server.
+    Timing,
+}
+
+impl std::fmt::Display for Deviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Deviation::Teleport => write!(f, "teleporting"),
+            Deviation::Speed => write!(f, "speeding"),
+            Deviation::Gravity => write!(f, "ignoring gravity"),
+            Deviation::Noclip => write!(f, "noclipping"),
+            Deviation::Timing => write!(f, "running its clock fast"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ClientMessage {
+    Connect {
+        version: u16,
+        name: String,
+    },
+    Update {
+        tick: u32,
+        state: PlayerState,
+        /// The latest inputs, resent until acknowledged so a lost packet doesn't lose movement.
+        inputs: Vec<InputFrame>,
+    },
+    /// Reached the end of the race level, resent until the server lists the finish.
+    Finished {
+        time: f32,
+    },
+    /// A finished level, announced when it beats the player's or the server's best.
+    LevelTime {
+        level: u8,
+        time: f32,
+    },
+    Chat {
+        text: String,
+    },
+    Disconnect,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ServerMessage {
+    Accepted {
+        id: PlayerId,
+        /// Clients play at the tick rate of the server, its simulation has to match theirs.
+        tick_rate: TickRate,
+    },
+    Rejected {
+        reason: String,
+    },
+    /// Split into parts of at most [`SNAPSHOT_PLAYERS`] players each.
+    Snapshot {
+        tick: u32,
+        /// The level every client has to be on, if the server enforces one.
+        level: Option<u8>,
+        part: u8,
+        parts: u8,
+        players: Vec<RemoteState>,
+    },
+    Disconnected {
+        id: PlayerId,
+    },
+    /// Sent along with every snapshot, `None` outside of a race.
+    Race(Option<RaceInfo>),
+    /// The server caught the receiving client moving in ways its inputs don't allow.
+    Invalidated(Deviation),
+    /// A chat message of a player, or of the server itself without a name.
+    Chat {
+        name: Option<String>,
+        text: String,
+    },
+}
+
+/// Strips control characters and cuts chat messages to the maximum length.
+pub fn sanitize_chat(text: &str) -> String {
+    text.chars()
+        .filter(|x| !x.is_control())
+        .take(MAX_CHAT_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Strips control characters and cuts names to the maximum length.
+pub fn sanitize_name(name: &str) -> String {
+    let name = name.chars().filter(|x| !x.is_control()).collect::<String>();
+    let name = name.trim();
+
+    let end = name
+        .char_indices()
+        .map(|(i, x)| i + x.len_utf8())
+        .take_while(|x| *x <= MAX_NAME_LENGTH)
+        .last()
+        .unwrap_or_default();
+
+    match name[..end].trim_end() {
+        "" => DEFAULT_NAME.to_string(),
+        x => x.to_string(),
+    }
+}
+
+pub fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    // Messages only contain plain data, serializing them can't fail.
+    bincode::serialize(message).expect("Network messages are serializable")
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    bincode::deserialize(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::client::REDUNDANT_INPUTS, *};
+
+    /// The longest name in bytes, made of multibyte characters.
+    fn long_name() -> String {
+        sanitize_name(&"ü".repeat(MAX_NAME_LENGTH))
+    }
+
+    fn snapshot(part: u8, players: usize) -> ServerMessage {
+        ServerMessage::Snapshot {
+            tick: u32::MAX,
+            level: Some(u8::MAX),
+            part,
+            parts: MAX_PLAYERS.div_ceil(SNAPSHOT_PLAYERS) as u8,
+            players: (0..players)
+                .map(|id| RemoteState {
+                    id: id as PlayerId,
+                    name: long_name(),
+                    tick: u32::MAX,
+                    state: PlayerState::default(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sanitize_name_caps_bytes() {
+        assert_eq!(sanitize_name("  bhop\n\tking  "), "bhopking");
+        assert_eq!(sanitize_name(&"a".repeat(40)).len(), MAX_NAME_LENGTH);
+        assert_eq!(long_name().len(), MAX_NAME_LENGTH);
+        // Never splits a character.
+        assert_eq!(sanitize_name(&format!("a{}", "ü".repeat(10))).len(), 15);
+        assert_eq!(sanitize_name(" \u{7} "), DEFAULT_NAME);
+    }
+
+    #[test]
+    fn snapshot_fits() {
+        let message = snapshot(0, SNAPSHOT_PLAYERS);
+        assert!(encode(&message).len() <= MAX_PACKET_SIZE);
+
+        let Some(ServerMessage::Snapshot { players, .. }) = decode(&encode(&message)) else {
+            panic!("Snapshot doesn't decode");
+        };
+        assert_eq!(players.len(), SNAPSHOT_PLAYERS);
+        assert_eq!(players[0].name, long_name());
+    }
+
+    #[test]
+    fn race_fits() {
+        let message = ServerMessage::Race(Some(RaceInfo {
+            level: u8::MAX,
+            countdown: 0.,
+            finished: (0..MAX_PLAYERS)
+                .map(|id| RaceFinish {
+                    id: id as PlayerId,
+                    name: long_name(),
+                    time: 0.,
+                })
+                .collect(),
+        }));
+
+        assert!(encode(&message).len() <= MAX_PACKET_SIZE);
+    }
+
+    #[test]
+    fn update_fits() {
+        let message = ClientMessage::Update {
+            tick: u32::MAX,
+            state: PlayerState::default(),
+            inputs: vec![
+                InputFrame {
+                    teleport: Some(Teleport::default()),
+                    ..default()
+                };
+                REDUNDANT_INPUTS
+            ],
+        };
+
+        assert!(encode(&message).len() <= MAX_PACKET_SIZE);
+    }
+
+    #[test]
+    fn chat_fits() {
+        let message = ServerMessage::Chat {
+            name: Some(long_name()),
+            text: sanitize_chat(&"ü".repeat(MAX_CHAT_LENGTH * 2)),
+        };
+
+        assert!(encode(&message).len() <= MAX_PACKET_SIZE);
+    }
+
+    #[test]
+    fn lerp_turns_the_short_way() {
+        let state = |yaw| PlayerState { yaw, ..default() };
+
+        let yaw = state(PI - 0.1).lerp(&state(-PI + 0.1), 0.5).yaw;
+        assert!((yaw - PI).abs() < 1e-5, "{yaw}");
+
+        let yaw = state(0.2).lerp(&state(TAU + 0.4), 0.5).yaw;
+        assert!((yaw - 0.3).abs() < 1e-5, "{yaw}");
+    }
+
+    #[test]
+    fn input_keys_roundtrip() {
+        for jump in [
+            None,
+            Some(JumpKind::Key),
+            Some(JumpKind::Scroll),
+            Some(JumpKind::Auto),
+        ] {
+            let keys = InputKeys {
+                forward: true,
+                right: true,
+                crouch: true,
+                jump,
+                ..default()
+            };
+
+            assert_eq!(encode(&keys).len(), 1);
+            assert_eq!(decode::<InputKeys>(&encode(&keys)), Some(keys));
+        }
+    }
+
+    #[test]
+    fn non_finite_frames() {
+        let frame = InputFrame::default();
+        assert!(frame.is_finite());
+
+        for frame in [
+            InputFrame {
+                movement: [f32::NAN, 0., 1.],
+                ..frame
+            },
+            InputFrame {
+                yaw: f32::INFINITY,
+                ..frame
+            },
+            InputFrame {
+                pitch: f32::NAN,
+                ..frame
+            },
+            InputFrame {
+                teleport: Some(Teleport {
+                    translation: [0., f32::NAN, 0.],
+                    ..default()
+                }),
+                ..frame
+            },
+            InputFrame {
+                teleport: Some(Teleport {
+                    velocity: [f32::NEG_INFINITY, 0., 0.],
+                    ..default()
+                }),
+                ..frame
+            },
+        ] {
+            // Survives the round trip, it is up to the server to drop it.
+            let decoded = decode::<InputFrame>(&encode(&frame)).unwrap();
+            assert!(!decoded.is_finite());
+        }
+
+        assert!(
+            !PlayerState {
+                run_time: f32::NAN,
+                ..default()
+            }
+            .is_finite()
+        );
+    }
+
+    #[test]
+    fn decode_rejects_garbage() {
+        assert!(decode::<ServerMessage>(&[0xff; 8]).is_none());
+        assert!(decode::<ServerMessage>(&encode(&snapshot(0, 1))[..12]).is_none());
+    }
+}
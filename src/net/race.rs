@@ -0,0 +1,342 @@
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::{core::*, duration::*, player::MovementSet, world::*};
+
+use super::*;
+
+pub const DEFAULT_COUNTDOWN: f32 = 5.;
+
+/// Races that take longer than this end with whoever finished so far.
+const RACE_TIME_LIMIT: f32 = 600.;
+
+/// How long the results stay up before the server goes back to the lobby.
+const RESULTS_TIME: f32 = 10.;
+
+/// A lost finish is sent again after this long.
+const FINISH_RETRY: Duration = Duration::from_millis(500);
+
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RaceStatus>()
+            .add_console_command::<RaceCommand, _>(race)
+            .add_systems(FixedUpdate, end_races.run_if(resource_exists::<Server>))
+            .add_systems(
+                Update,
+                (update_race_state, report_finish)
+                    .chain()
+                    .run_if(resource_exists::<Client>),
+            )
+            .add_systems(Update, reset_race.run_if(resource_removed::<Client>))
+            .add_systems(
+                FixedUpdate,
+                freeze_countdown
+                    .after(MovementSet)
+                    .run_if(in_state(RaceState::Countdown)),
+            )
+            .add_systems(OnEnter(RaceState::Countdown), prepare_race)
+            .add_systems(OnEnter(RaceState::Racing), reset_run_duration);
+    }
+}
+
+/// A race run by the server, started with the `race` command or by a dedicated server in race mode.
+#[derive(Debug, Clone)]
+pub struct Race {
+    pub level: u8,
+    /// The server tick the race starts on.
+    pub start: u32,
+    /// In finishing order.
+    pub finished: Vec<RaceFinish>,
+}
+
+impl Race {
+    pub fn new(level: u8, start: u32) -> Self {
+        Self {
+            level,
+            start,
+            finished: default(),
+        }
+    }
+
+    pub fn is_running(&self, tick: u32) -> bool {
+        tick.wrapping_sub(self.start) as i32 >= 0
+    }
+
+    /// Seconds since the start at `tick`.
+    pub fn elapsed(&self, tick: u32, timestep: Duration) -> f32 {
+        (tick.wrapping_sub(self.start) as i32).max(0) as f32 * timestep.as_secs_f32()
+    }
+
+    /// Returns the place of the finish.
+    pub(super) fn finish(&mut self, finish: RaceFinish) -> usize {
+        let place = self.finished.partition_point(|x| x.time <= finish.time);
+        self.finished.insert(place, finish);
+        place + 1
+    }
+
+    /// Lists the first [`MAX_PLAYERS`] finishes, players that reconnected finish more than once.
+    pub fn info(&self, tick: u32, timestep: Duration) -> RaceInfo {
+        RaceInfo {
+            level: self.level,
+            countdown: (self.start.wrapping_sub(tick) as i32).max(0) as f32
+                * timestep.as_secs_f32(),
+            finished: self.finished.iter().take(MAX_PLAYERS).cloned().collect(),
+        }
+    }
+}
+
+/// The race the client takes part in, as last received from the server.
+#[derive(Resource, Default, Debug)]
+pub struct RaceStatus {
+    pub info: Option<RaceInfo>,
+    /// When the race starts, estimated from the countdown of the server.
+    starts_at: Option<Duration>,
+    /// The time of the local finish, sent until the server lists it.
+    finish: Option<f32>,
+}
+
+impl RaceStatus {
+    pub(super) fn receive(&mut self, info: Option<RaceInfo>, now: Duration) {
+        let Some(info) = info else {
+            *self = default();
+            return;
+        };
+
+        if info.countdown > 0. || self.starts_at.is_none() {
+            self.starts_at = Some(now + Duration::from_secs_f32(info.countdown));
+        }
+
+        self.info = Some(info);
+    }
+
+    /// Seconds until the start.
+    pub fn countdown(&self, now: Duration) -> f32 {
+        self.starts_at
+            .map_or(0., |x| x.saturating_sub(now).as_secs_f32())
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "race")]
+struct RaceCommand {
+    /// The level to race on, the current one by default.
+    #[arg(index = 1)]
+    level: Option<usize>,
+    /// Seconds until the start.
+    #[arg(long, default_value_t = DEFAULT_COUNTDOWN)]
+    countdown: f32,
+}
+
+fn race(
+    mut log: ConsoleCommand<RaceCommand>,
+    server: Option<ResMut<Server>>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Fixed>>,
+) {
+    let Some(Ok(RaceCommand { level, countdown })) = log.take() else {
+        return;
+    };
+
+    let Some(mut server) = server else {
+        reply!(log, "Only the host can start a race.");
+        return;
+    };
+
+    if server.race.is_some() {
+        reply!(log, "A race is already running.");
+        return;
+    }
+
+    let level = level.unwrap_or(current_level.get().get());
+
+    if !(1..=LEVEL_COUNT).contains(&level) {
+        reply!(log, "There is no level {level}.");
+        return;
+    }
+
+    let countdown = countdown.max(0.);
+
+    server.start_race(
+        level as u8,
+        Duration::from_secs_f32(countdown),
+        time.timestep(),
+    );
+
+    reply!(log, "Race on level {level} starts in {countdown:.0}s.");
+}
+
+/// Ends a race once everyone finished or the time limit is up, the results stay up for a while.
+fn end_races(mut server: ResMut<Server>, time: Res<Time<Fixed>>, mut ends: Local<Option<u32>>) {
+    let tick = server.tick();
+
+    let Some(race) = server.race.as_ref().filter(|x| x.is_running(tick)) else {
+        *ends = None;
+        return;
+    };
+
+    let Some(end) = *ends else {
+        let finished = server
+            .players()
+            .all(|(id, _, _)| race.finished.iter().any(|x| x.id == id));
+
+        if finished || race.elapsed(tick, time.timestep()) > RACE_TIME_LIMIT {
+            info!("Race over, {} finished.", race.finished.len());
+
+            *ends = Some(
+                tick.wrapping_add((RESULTS_TIME / time.timestep().as_secs_f32()).ceil() as u32),
+            );
+        }
+
+        return;
+    };
+
+    if tick.wrapping_sub(end) as i32 >= 0 {
+        server.race = None;
+        *ends = None;
+    }
+}
+
+fn update_race_state(
+    client: Res<Client>,
+    status: Res<RaceStatus>,
+    time: Res<Time<Real>>,
+    state: Res<State<RaceState>>,
+    mut ns: ResMut<NextState<RaceState>>,
+) {
+    let next = match &status.info {
+        None => RaceState::None,
+        Some(info)
+            if client
+                .id()
+                .is_some_and(|id| info.finished.iter().any(|x| x.id == id)) =>
+        {
+            RaceState::Finished
+        }
+        Some(_) if status.countdown(time.elapsed()) > 0. => RaceState::Countdown,
+        Some(_) if matches!(state.get(), RaceState::None | RaceState::Watching) => {
+            RaceState::Watching
+        }
+        Some(_) => RaceState::Racing,
+    };
+
+    if *state.get() != next {
+        ns.set(next);
+    }
+}
+
+fn report_finish(
+    mut er: EventReader<LevelFinished>,
+    q_player: Query<(), With<LogicalPlayer>>,
+    mut status: ResMut<RaceStatus>,
+    state: Res<State<RaceState>>,
+    client: Res<Client>,
+    level_duration: Res<LevelDuration>,
+    time: Res<Time<Real>>,
+    mut last_sent: Local<Option<Duration>>,
+) {
+    for e in er.read() {
+        if *state.get() == RaceState::Racing
+            && status.finish.is_none()
+            && q_player.contains(e.entity)
+        {
+            status.finish = Some(level_duration.0.elapsed_secs());
+        }
+    }
+
+    let Some(time_finished) = status.finish.filter(|_| *state.get() == RaceState::Racing) else {
+        return;
+    };
+
+    let now = time.elapsed();
+
+    if last_sent.is_some_and(|x| now.saturating_sub(x) < FINISH_RETRY) {
+        return;
+    }
+
+    *last_sent = Some(now);
+    client.send(&ClientMessage::Finished {
+        time: time_finished,
+    });
+}
+
+fn reset_race(mut status: ResMut<RaceStatus>, mut ns: ResMut<NextState<RaceState>>) {
+    *status = default();
+    ns.set(RaceState::None);
+}
+
+fn prepare_race(mut history: ResMut<History>, mut ew: EventWriter<Respawn<LogicalPlayer>>) {
+    history.0.clear();
+    ew.write(Respawn::<LogicalPlayer>::new(SPAWN_POINT));
+}
+
+/// Holds the player on the spawn until the race starts, looking around still works.
+fn freeze_countdown(
+    mut q_player: Query<
+        (&mut Transform, &mut LinearVelocity, &mut FpsControllerInput),
+        With<LogicalPlayer>,
+    >,
+) {
+    for (mut transform, mut velocity, mut input) in &mut q_player {
+        transform.translation = SPAWN_POINT;
+        velocity.0 = Vec3::ZERO;
+        input.movement = Vec3::ZERO;
+        input.jump = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finish(id: PlayerId, time: f32) -> RaceFinish {
+        RaceFinish {
+            id,
+            name: id.to_string(),
+            time,
+        }
+    }
+
+    #[test]
+    fn finish_orders_by_time() {
+        let mut race = Race::new(1, 0);
+
+        assert_eq!(race.finish(finish(1, 30.)), 1);
+        assert_eq!(race.finish(finish(2, 20.)), 1);
+        assert_eq!(race.finish(finish(3, 40.)), 3);
+        // Ties keep the earlier finish ahead.
+        assert_eq!(race.finish(finish(4, 30.)), 3);
+
+        let ids = race.finished.iter().map(|x| x.id).collect::<Vec<_>>();
+        assert_eq!(ids, [2, 1, 4, 3]);
+    }
+
+    #[test]
+    fn info_counts_down() {
+        let race = Race::new(1, 100);
+        // 128 Hz, so the countdown is exact.
+        let timestep = Duration::from_nanos(7_812_500);
+
+        assert_eq!(race.info(36, timestep).countdown, 0.5);
+        assert_eq!(race.info(150, timestep).countdown, 0.);
+        assert!(!race.is_running(99));
+        assert!(race.is_running(100));
+    }
+
+    #[test]
+    fn info_caps_finishes() {
+        let mut race = Race::new(1, 0);
+
+        for id in 0..MAX_PLAYERS as PlayerId + 4 {
+            race.finish(finish(id, id as f32));
+        }
+
+        assert_eq!(race.info(0, Duration::ZERO).finished.len(), MAX_PLAYERS);
+    }
+}
@@ -0,0 +1,611 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::{preset::TickRate, records::RunCategory};
+
+use super::*;
+
+/// Clients that didn't send anything for this long are dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Snapshots are sent every n-th fixed tick, 32 Hz at the default 128 Hz tickrate.
+const SNAPSHOT_INTERVAL: u32 = 4;
+
+/// Inputs a client can be ahead of the simulation, older ones are dropped.
+const MAX_QUEUED_INPUTS: usize = 32;
+
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerJoined>()
+            .add_event::<PlayerLeft>()
+            .add_systems(
+                Update,
+                (server_receive, server_timeout)
+                    .chain()
+                    .run_if(resource_exists::<Server>),
+            )
+            .add_systems(
+                FixedUpdate,
+                server_broadcast.run_if(resource_exists::<Server>),
+            );
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerJoined {
+    pub id: PlayerId,
+    pub name: String,
+}
+
+#[derive(Event)]
+pub struct PlayerLeft {
+    pub id: PlayerId,
+}
+
+struct Connection {
+    id: PlayerId,
+    name: String,
+    state: PlayerState,
+    /// The last client tick reflected in `state`.
+    tick: u32,
+    inputs: VecDeque<InputFrame>,
+    last_seen: Duration,
+    /// Best level times in seconds during this session.
+    bests: HashMap<(u8, RunCategory), f32>,
+    validator: MovementValidator,
+    /// Why the run of the player doesn't count, until the next level.
+    invalidated: Option<Deviation>,
+    /// Dropped with the next timeout check.
+    kick: Option<String>,
+}
+
+#[derive(Resource)]
+pub struct Server {
+    socket: UdpSocket,
+    connections: HashMap<SocketAddr, Connection>,
+    max_players: usize,
+    next_id: PlayerId,
+    tick: u32,
+    /// Set by dedicated servers to keep every client on the same level.
+    pub level: Option<u8>,
+    /// Set by dedicated servers that simulate the movement of every player from their inputs.
+    pub authoritative: bool,
+    pub race: Option<Race>,
+    pub validation: ValidationConfig,
+    pub tick_rate: TickRate,
+    /// Best level times of the session with the name of the player that set them.
+    records: HashMap<(u8, RunCategory), (String, f32)>,
+}
+
+impl Server {
+    /// Takes up to `max_players`, at most [`MAX_PLAYERS`].
+    pub fn bind(port: u16, max_players: usize) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+
+        if max_players > MAX_PLAYERS {
+            warn!("Servers take up to {MAX_PLAYERS} players, not {max_players}.");
+        }
+
+        Ok(Self {
+            socket,
+            connections: HashMap::new(),
+            max_players: max_players.min(MAX_PLAYERS),
+            next_id: 1,
+            tick: 0,
+            level: None,
+            authoritative: false,
+            race: None,
+            validation: default(),
+            tick_rate: default(),
+            records: HashMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// The fixed tick of the server, advanced with every broadcast.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = (PlayerId, &str, &PlayerState)> {
+        self.connections
+            .values()
+            .map(|x| (x.id, x.name.as_str(), &x.state))
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<(&str, &PlayerState)> {
+        self.connections
+            .values()
+            .find(|x| x.id == id)
+            .map(|x| (x.name.as_str(), &x.state))
+    }
+
+    /// Takes the oldest input of a player that wasn't simulated yet, skipping ones with NaN or infinite values.
+    pub fn next_input(&mut self, id: PlayerId) -> Option<InputFrame> {
+        let connection = self.connections.values_mut().find(|x| x.id == id)?;
+
+        while let Some(frame) = connection.inputs.pop_front() {
+            match frame.is_finite() {
+                true => return Some(frame),
+                false => debug!("Dropping a non-finite input of player {id}."),
+            }
+        }
+
+        None
+    }
+
+    /// Stores the simulated state of a player, acknowledging its inputs up to `tick`.
+    pub fn acknowledge(&mut self, id: PlayerId, tick: u32, translation: Vec3, velocity: Vec3) {
+        let Some(connection) = self.connections.values_mut().find(|x| x.id == id) else {
+            return;
+        };
+
+        connection.tick = tick;
+        connection.state.translation = translation.to_array();
+        connection.state.velocity = velocity.to_array();
+
+        // Players on another level aren't simulated against its colliders.
+        if self.level.is_some_and(|x| x != connection.state.level) {
+            return;
+        }
+
+        if let Some(deviation) =
+            connection
+                .validator
+                .check(tick, translation, velocity, &self.validation)
+        {
+            self.punish(id, deviation);
+        }
+    }
+
+    /// Flags an input the server won't simulate, e.g. a respawn that didn't land on the spawn or a checkpoint.
+    pub fn reject(&mut self, id: PlayerId, deviation: Deviation) {
+        let Some(connection) = self.connections.values_mut().find(|x| x.id == id) else {
+            return;
+        };
+
+        // The first input picks up wherever the player already is.
+        if connection.tick == 0 || self.level.is_some_and(|x| x != connection.state.level) {
+            return;
+        }
+
+        if let Some(deviation) = connection.validator.flag(deviation) {
+            self.punish(id, deviation);
+        }
+    }
+
+    /// Forgets about earlier deviations, every player gets a clean slate on a new level.
+    pub fn reset_validation(&mut self) {
+        for connection in self.connections.values_mut() {
+            connection.validator = default();
+            connection.invalidated = None;
+        }
+    }
+
+    /// Kicks the player or invalidates its run, depending on the validation config.
+    fn punish(&mut self, id: PlayerId, deviation: Deviation) {
+        let kick = self.validation.kick;
+
+        let Some((addr, connection)) = self.connections.iter_mut().find(|(_, x)| x.id == id) else {
+            return;
+        };
+
+        let addr = *addr;
+        let name = connection.name.clone();
+
+        warn!("{name} deviated from the simulation: {deviation}.");
+
+        if kick {
+            connection.kick = Some(format!("Kicked for {deviation}."));
+            self.say(format!("{name} was kicked for {deviation}."));
+            return;
+        }
+
+        connection.invalidated = Some(deviation);
+        self.send(addr, &ServerMessage::Invalidated(deviation));
+        self.say(format!("{name}'s run was invalidated for {deviation}."));
+    }
+
+    /// Moves everyone to `level` and starts a race there after the countdown.
+    pub fn start_race(&mut self, level: u8, countdown: Duration, timestep: Duration) {
+        let ticks = (countdown.as_secs_f32() / timestep.as_secs_f32()).ceil() as u32;
+
+        info!(
+            "Starting a race on level {level} in {:.0}s.",
+            countdown.as_secs_f32()
+        );
+
+        self.level = Some(level);
+        self.race = Some(Race::new(level, self.tick.wrapping_add(ticks)));
+    }
+
+    /// Records the finish of a player, ignored outside of a running race or when already finished.
+    pub fn finish(&mut self, id: PlayerId, time: f32) {
+        let Some(race) = self.race.as_mut().filter(|x| x.is_running(self.tick)) else {
+            return;
+        };
+
+        if race.finished.iter().any(|x| x.id == id) {
+            return;
+        }
+
+        let Some(connection) = self
+            .connections
+            .values()
+            .find(|x| x.id == id && x.invalidated.is_none())
+        else {
+            return;
+        };
+
+        let name = connection.name.clone();
+        let place = race.finish(RaceFinish {
+            id,
+            name: name.clone(),
+            time,
+        });
+
+        self.say(format!("{name} finished #{place} in {time:.3}s."));
+    }
+
+    /// Announces a finished level if it is a personal best or a server record.
+    pub fn record_time(&mut self, id: PlayerId, level: u8, time: f32) {
+        let Some(connection) = self
+            .connections
+            .values_mut()
+            .find(|x| x.id == id && x.invalidated.is_none())
+        else {
+            return;
+        };
+
+        let category = connection.state.category;
+        let key = (level, category);
+        let previous = connection.bests.get(&key).copied();
+
+        if previous.is_some_and(|x| time >= x) {
+            return;
+        }
+
+        connection.bests.insert(key, time);
+
+        let name = connection.name.clone();
+
+        if self.records.get(&key).is_none_or(|(_, x)| time < *x) {
+            self.records.insert(key, (name.clone(), time));
+            self.say(format!(
+                "{name} set a server record on level {level} ({category}): {time:.3}s!"
+            ));
+            return;
+        }
+
+        self.say(match previous {
+            Some(previous) => format!(
+                "{name} beat their best on level {level} ({category}): {time:.3}s (-{:.3}s).",
+                previous - time
+            ),
+            None => format!("{name} finished level {level} ({category}) in {time:.3}s."),
+        });
+    }
+
+    /// Sends a chat message from the server itself to everyone.
+    pub fn say(&self, text: impl Into<String>) {
+        let text = text.into();
+
+        info!("{text}");
+
+        self.broadcast(&ServerMessage::Chat { name: None, text });
+    }
+
+    fn send(&self, addr: SocketAddr, message: &ServerMessage) {
+        if let Err(e) = self.socket.send_to(&encode(message), addr) {
+            warn!("Could not send to {addr}: {e}");
+        }
+    }
+
+    fn broadcast(&self, message: &ServerMessage) {
+        for addr in self.connections.keys() {
+            self.send(*addr, message);
+        }
+    }
+
+    /// Returns the id of the player if it just joined.
+    fn accept(
+        &mut self,
+        addr: SocketAddr,
+        version: u16,
+        name: String,
+        now: Duration,
+    ) -> Option<PlayerId> {
+        if let Some(connection) = self.connections.get(&addr) {
+            // The accept got lost, the client is still waiting for it.
+            self.send(
+                addr,
+                &ServerMessage::Accepted {
+                    id: connection.id,
+                    tick_rate: self.tick_rate,
+                },
+            );
+            return None;
+        }
+
+        let reason = if version != PROTOCOL_VERSION {
+            Some(format!(
+                "Protocol version {version} doesn't match the server's {PROTOCOL_VERSION}."
+            ))
+        } else if self.connections.len() >= self.max_players {
+            Some(String::from("The server is full."))
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            self.send(addr, &ServerMessage::Rejected { reason });
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        info!("{name} ({addr}) joined as player {id}.");
+
+        self.connections.insert(
+            addr,
+            Connection {
+                id,
+                name,
+                state: default(),
+                tick: 0,
+                inputs: default(),
+                last_seen: now,
+                bests: HashMap::new(),
+                validator: default(),
+                invalidated: None,
+                kick: None,
+            },
+        );
+
+        self.send(
+            addr,
+            &ServerMessage::Accepted {
+                id,
+                tick_rate: self.tick_rate,
+            },
+        );
+
+        Some(id)
+    }
+
+    fn disconnect(&mut self, addr: SocketAddr, reason: &str) -> Option<PlayerId> {
+        let connection = self.connections.remove(&addr)?;
+
+        info!("{} left ({reason}).", connection.name);
+
+        self.broadcast(&ServerMessage::Disconnected { id: connection.id });
+
+        Some(connection.id)
+    }
+}
+
+fn server_receive(
+    mut server: ResMut<Server>,
+    time: Res<Time<Real>>,
+    mut ew_joined: EventWriter<PlayerJoined>,
+    mut ew_left: EventWriter<PlayerLeft>,
+) {
+    let mut buf = [0; MAX_PACKET_SIZE];
+    let now = time.elapsed();
+
+    loop {
+        let (len, addr) = match server.socket.recv_from(&mut buf) {
+            Ok(x) => x,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+            // Windows reports ICMP port unreachable of earlier sends as errors here.
+            Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+            Err(e) => {
+                warn!("Server receive failed: {e}");
+                return;
+            }
+        };
+
+        let Some(message) = decode::<ClientMessage>(&buf[..len]) else {
+            continue;
+        };
+
+        match message {
+            ClientMessage::Connect { version, name } => {
+                let name = sanitize_name(&name);
+
+                if let Some(id) = server.accept(addr, version, name.clone(), now) {
+                    ew_joined.write(PlayerJoined { id, name });
+                }
+            }
+            ClientMessage::Update {
+                tick,
+                state,
+                inputs,
+            } => {
+                let authoritative = server.authoritative;
+                let server_tick = server.tick;
+                let validation = server.validation;
+
+                let Some(connection) = server.connections.get_mut(&addr) else {
+                    continue;
+                };
+
+                connection.last_seen = now;
+
+                if !state.is_finite() {
+                    continue;
+                }
+
+                if !authoritative {
+                    connection.state = state;
+                    connection.tick = tick;
+                    continue;
+                }
+
+                let id = connection.id;
+                let deviation = connection
+                    .validator
+                    .report(tick, state, server_tick, &validation);
+
+                // Movement comes from the simulation, everything else is up to the client.
+                connection.state = PlayerState {
+                    translation: connection.state.translation,
+                    velocity: connection.state.velocity,
+                    ..state
+                };
+
+                let last = connection.inputs.back().map_or(connection.tick, |x| x.tick);
+
+                for input in inputs
+                    .into_iter()
+                    .filter(|x| x.tick.wrapping_sub(last) as i32 > 0)
+                {
+                    if connection.inputs.len() >= MAX_QUEUED_INPUTS {
+                        connection.inputs.pop_front();
+                    }
+                    connection.inputs.push_back(input);
+                }
+
+                if let Some(deviation) = deviation {
+                    server.punish(id, deviation);
+                }
+            }
+            ClientMessage::Finished { time } => {
+                let Some(id) = server
+                    .connections
+                    .get(&addr)
+                    .filter(|_| !server.authoritative)
+                    .map(|x| x.id)
+                else {
+                    continue;
+                };
+
+                server.finish(id, time);
+            }
+            ClientMessage::LevelTime { level, time } => {
+                // Authoritative servers time the levels themselves.
+                let Some(id) = server
+                    .connections
+                    .get(&addr)
+                    .filter(|_| !server.authoritative)
+                    .map(|x| x.id)
+                else {
+                    continue;
+                };
+
+                server.record_time(id, level, time);
+            }
+            ClientMessage::Chat { text } => {
+                let text = sanitize_chat(&text);
+
+                let Some(name) = server
+                    .connections
+                    .get(&addr)
+                    .filter(|_| !text.is_empty())
+                    .map(|x| x.name.clone())
+                else {
+                    continue;
+                };
+
+                info!("{name}: {text}");
+
+                server.broadcast(&ServerMessage::Chat {
+                    name: Some(name),
+                    text,
+                });
+            }
+            ClientMessage::Disconnect => {
+                if let Some(id) = server.disconnect(addr, "disconnected") {
+                    ew_left.write(PlayerLeft { id });
+                }
+            }
+        }
+    }
+}
+
+/// Drops clients that timed out or were kicked.
+fn server_timeout(
+    mut server: ResMut<Server>,
+    time: Res<Time<Real>>,
+    mut ew: EventWriter<PlayerLeft>,
+) {
+    let now = time.elapsed();
+
+    let dropped = server
+        .connections
+        .iter()
+        .filter_map(|(addr, x)| match &x.kick {
+            Some(reason) => Some((*addr, Some(reason.clone()))),
+            None => (now.saturating_sub(x.last_seen) > CLIENT_TIMEOUT).then_some((*addr, None)),
+        })
+        .collect::<Vec<_>>();
+
+    for (addr, kick) in dropped {
+        let reason = match kick {
+            Some(reason) => {
+                server.send(addr, &ServerMessage::Rejected { reason });
+                "kicked"
+            }
+            None => "timed out",
+        };
+
+        if let Some(id) = server.disconnect(addr, reason) {
+            ew.write(PlayerLeft { id });
+        }
+    }
+}
+
+fn server_broadcast(mut server: ResMut<Server>, time: Res<Time<Fixed>>) {
+    server.tick = server.tick.wrapping_add(1);
+
+    if server.tick % SNAPSHOT_INTERVAL != 0 {
+        return;
+    }
+
+    let players = server
+        .connections
+        .values()
+        .map(|x| RemoteState {
+            id: x.id,
+            name: x.name.clone(),
+            tick: x.tick,
+            state: x.state,
+        })
+        .collect::<Vec<_>>();
+
+    let parts = players.chunks(SNAPSHOT_PLAYERS).count() as u8;
+
+    for (part, players) in players.chunks(SNAPSHOT_PLAYERS).enumerate() {
+        server.broadcast(&ServerMessage::Snapshot {
+            tick: server.tick,
+            level: server.level,
+            part: part as u8,
+            parts,
+            players: players.to_vec(),
+        });
+    }
+
+    server.broadcast(&ServerMessage::Race(
+        server
+            .race
+            .as_ref()
+            .map(|x| x.info(server.tick, time.timestep())),
+    ));
+}
@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::*;
+
+/// Reported states waiting for the simulation, 1s at the default 128 Hz tickrate and send rate.
+const MAX_REPORTED: usize = 64;
+
+/// Thresholds of the movement validation done by authoritative servers.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Reported positions further off the simulated ones count as teleports, as do respawns away from the spawn and checkpoints.
+    pub teleport_distance: f32,
+    /// Reported horizontal speed above the simulated one by more than this counts as speeding.
+    pub speed_tolerance: f32,
+    /// Reported height above the simulated one, while not falling as fast, counts as ignoring gravity.
+    pub gravity_tolerance: f32,
+    /// Ticks the clock of a client may run ahead of the server's, more is a sped up client.
+    pub max_tick_drift: u32,
+    /// Every deviating state adds one, punished once it reaches this.
+    pub threshold: f32,
+    /// Taken off for every state that matches the simulation.
+    pub decay: f32,
+    /// Kick instead of only invalidating the run.
+    pub kick: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            teleport_distance: 4.,
+            speed_tolerance: 2.,
+            gravity_tolerance: 1.,
+            max_tick_drift: 64,
+            threshold: 32.,
+            decay: 0.5,
+            kick: false,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Respawns have to land on the spawn or a checkpoint the player touched, without any speed.
+    pub fn allows_teleport(
+        &self,
+        teleport: &Teleport,
+        mut respawn_points: impl Iterator<Item = Vec3>,
+    ) -> bool {
+        let translation = Vec3::from_array(teleport.translation);
+
+        Vec3::from_array(teleport.velocity).length() <= self.speed_tolerance
+            && respawn_points.any(|x| x.distance(translation) <= self.teleport_distance)
+    }
+
+    fn deviation(
+        &self,
+        reported: &PlayerState,
+        translation: Vec3,
+        velocity: Vec3,
+    ) -> Option<Deviation> {
+        let offset = reported.translation() - translation;
+
+        if offset.length() > self.teleport_distance {
+            return Some(Deviation::Teleport);
+        }
+
+        if reported.velocity().xz().length() - velocity.xz().length() > self.speed_tolerance {
+            return Some(Deviation::Speed);
+        }
+
+        if offset.y > self.gravity_tolerance && reported.velocity().y > velocity.y {
+            return Some(Deviation::Gravity);
+        }
+
+        None
+    }
+}
+
+/// Compares what a client reports with what the server simulated from its inputs.
+///
+/// Mispredictions are corrected within a round trip, only sustained deviations are flagged.
+#[derive(Default)]
+pub(super) struct MovementValidator {
+    /// Reported states waiting for the simulation to reach their tick.
+    reported: VecDeque<(u32, PlayerState)>,
+    /// The smallest lead of the client's ticks over the server's, the baseline of its clock drift.
+    min_lead: Option<i32>,
+    suspicion: f32,
+    last: Option<Deviation>,
+    flagged: bool,
+}
+
+impl MovementValidator {
+    /// Queues a reported state until the simulation reaches its tick and checks the clock of the client.
+    pub(super) fn report(
+        &mut self,
+        tick: u32,
+        state: PlayerState,
+        server_tick: u32,
+        config: &ValidationConfig,
+    ) -> Option<Deviation> {
+        if self.reported.len() >= MAX_REPORTED {
+            self.reported.pop_front();
+        }
+        self.reported.push_back((tick, state));
+
+        let lead = tick.wrapping_sub(server_tick) as i32;
+        let min_lead = self.min_lead.map_or(lead, |x| x.min(lead));
+        self.min_lead = Some(min_lead);
+
+        // A client ticking faster than the server gets further and further ahead, lag only holds it back.
+        match lead - min_lead > config.max_tick_drift as i32 {
+            true => self.observe(Some(Deviation::Timing), config),
+            false => None,
+        }
+    }
+
+    /// Compares the simulated state at `tick` with the one the client reported for it.
+    pub(super) fn check(
+        &mut self,
+        tick: u32,
+        translation: Vec3,
+        velocity: Vec3,
+        config: &ValidationConfig,
+    ) -> Option<Deviation> {
+        while self
+            .reported
+            .front()
+            .is_some_and(|(x, _)| tick.wrapping_sub(*x) as i32 > 0)
+        {
+            self.reported.pop_front();
+        }
+
+        let (_, reported) = self.reported.front().filter(|(x, _)| *x == tick).copied()?;
+        self.reported.pop_front();
+
+        self.observe(config.deviation(&reported, translation, velocity), config)
+    }
+
+    /// Flags right away, for deviations that can't be rounding or lag.
+    pub(super) fn flag(&mut self, deviation: Deviation) -> Option<Deviation> {
+        if self.flagged {
+            return None;
+        }
+
+        self.flagged = true;
+        Some(deviation)
+    }
+
+    fn observe(
+        &mut self,
+        deviation: Option<Deviation>,
+        config: &ValidationConfig,
+    ) -> Option<Deviation> {
+        match deviation {
+            Some(x) => {
+                self.suspicion += 1.;
+                self.last = Some(x);
+            }
+            None => self.suspicion = (self.suspicion - config.decay).max(0.),
+        }
+
+        if self.flagged || self.suspicion < config.threshold {
+            return None;
+        }
+
+        self.flagged = true;
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(translation: Vec3, velocity: Vec3) -> PlayerState {
+        PlayerState {
+            translation: translation.to_array(),
+            velocity: velocity.to_array(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn deviation() {
+        let config = ValidationConfig::default();
+        let at = |translation, velocity| {
+            config.deviation(&state(translation, velocity), Vec3::ZERO, Vec3::ZERO)
+        };
+
+        assert_eq!(at(Vec3::X, Vec3::X), None);
+        assert_eq!(at(Vec3::X * 5., Vec3::ZERO), Some(Deviation::Teleport));
+        assert_eq!(at(Vec3::ZERO, Vec3::X * 3.), Some(Deviation::Speed));
+        assert_eq!(at(Vec3::Y * 2., Vec3::Y), Some(Deviation::Gravity));
+        // Falling faster than the simulation is no advantage.
+        assert_eq!(at(Vec3::Y * 2., Vec3::NEG_Y), None);
+    }
+
+    #[test]
+    fn flags_sustained_deviations_once() {
+        let config = ValidationConfig {
+            threshold: 3.,
+            ..default()
+        };
+        let mut validator = MovementValidator::default();
+
+        let check = |validator: &mut MovementValidator, tick, translation| {
+            validator.report(tick, state(translation, Vec3::ZERO), tick, &config);
+            validator.check(tick, Vec3::ZERO, Vec3::ZERO, &config)
+        };
+
+        // Matching states work off earlier deviations.
+        assert_eq!(check(&mut validator, 1, Vec3::X * 5.), None);
+        assert_eq!(check(&mut validator, 2, Vec3::X * 5.), None);
+        for tick in 3..7 {
+            assert_eq!(check(&mut validator, tick, Vec3::ZERO), None);
+        }
+
+        assert_eq!(check(&mut validator, 7, Vec3::X * 5.), None);
+        assert_eq!(check(&mut validator, 8, Vec3::X * 5.), None);
+        assert_eq!(
+            check(&mut validator, 9, Vec3::X * 5.),
+            Some(Deviation::Teleport)
+        );
+        assert_eq!(check(&mut validator, 10, Vec3::X * 5.), None);
+        assert_eq!(validator.flag(Deviation::Gravity), None);
+    }
+
+    #[test]
+    fn check_waits_for_the_reported_tick() {
+        let config = ValidationConfig {
+            threshold: 1.,
+            ..default()
+        };
+        let mut validator = MovementValidator::default();
+
+        validator.report(5, state(Vec3::X * 5., Vec3::ZERO), 5, &config);
+        assert_eq!(validator.check(4, Vec3::ZERO, Vec3::ZERO, &config), None);
+        assert_eq!(
+            validator.check(5, Vec3::ZERO, Vec3::ZERO, &config),
+            Some(Deviation::Teleport)
+        );
+    }
+
+    #[test]
+    fn sped_up_clocks() {
+        let config = ValidationConfig {
+            threshold: 1.,
+            max_tick_drift: 10,
+            ..default()
+        };
+        let mut validator = MovementValidator::default();
+
+        // A constant lead is only latency.
+        assert_eq!(validator.report(105, default(), 100, &config), None);
+        assert_eq!(validator.report(205, default(), 200, &config), None);
+        assert_eq!(
+            validator.report(320, default(), 300, &config),
+            Some(Deviation::Timing)
+        );
+    }
+
+    #[test]
+    fn teleports_to_touched_respawns() {
+        let config = ValidationConfig::default();
+        let checkpoints = [Vec3::ZERO, Vec3::X * 100.];
+        let teleport = |translation: Vec3, velocity: Vec3| Teleport {
+            translation: translation.to_array(),
+            velocity: velocity.to_array(),
+        };
+
+        assert!(config.allows_teleport(
+            &teleport(Vec3::X * 101., Vec3::ZERO),
+            checkpoints.into_iter()
+        ));
+        assert!(!config.allows_teleport(
+            &teleport(Vec3::X * 50., Vec3::ZERO),
+            checkpoints.into_iter()
+        ));
+        assert!(!config.allows_teleport(
+            &teleport(Vec3::ZERO, Vec3::X * 10.),
+            checkpoints.into_iter()
+        ));
+        assert!(!config.allows_teleport(&teleport(Vec3::ZERO, Vec3::ZERO), std::iter::empty()));
+    }
+}
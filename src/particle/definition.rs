@@ -0,0 +1,258 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use bevy_hanabi::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// An artist facing description of a particle effect, compiled into an [`EffectAsset`] on load.
+///
+/// Definitions live in `assets/effects/*.effect.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct EffectDefinition {
+    pub name: String,
+    pub capacity: u32,
+    pub spawner: SpawnerDefinition,
+    /// Radius of the sphere particles are spawned in.
+    pub radius: f32,
+    /// Uniform range of the initial particle speed.
+    pub speed: (f32, f32),
+    /// Uniform range of the initial particle age.
+    pub age: (f32, f32),
+    /// Mean and standard deviation of the particle lifetime.
+    pub lifetime: (f32, f32),
+    #[serde(default)]
+    pub accel: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub drag: Option<f32>,
+    #[serde(default)]
+    pub tangent_accel: Option<TangentAccelDefinition>,
+    #[serde(default)]
+    pub round: bool,
+    pub orient: OrientDefinition,
+    #[serde(default)]
+    pub simulation_space: SimulationSpaceDefinition,
+    /// Name of a `Vec4` property the particle color is multiplied with, set at runtime through [`EffectProperties`].
+    #[serde(default)]
+    pub color_property: Option<String>,
+    /// Name of a `f32` property the particle lifetime is multiplied with.
+    #[serde(default)]
+    pub lifetime_property: Option<String>,
+    /// Color over lifetime keys, linear RGBA.
+    pub color: Vec<(f32, (f32, f32, f32, f32))>,
+    /// Size over lifetime keys.
+    pub size: Vec<(f32, f32)>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum SpawnerDefinition {
+    Once { count: f32 },
+    Rate { rate: f32 },
+    Burst { count: f32, period: f32 },
+}
+
+impl SpawnerDefinition {
+    fn settings(&self) -> SpawnerSettings {
+        match *self {
+            Self::Once { count } => SpawnerSettings::once(count.into()),
+            Self::Rate { rate } => SpawnerSettings::rate(rate.into()),
+            Self::Burst { count, period } => SpawnerSettings::burst(count.into(), period.into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct TangentAccelDefinition {
+    pub axis: (f32, f32, f32),
+    pub accel: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum OrientDefinition {
+    ParallelCameraDepthPlane,
+    FaceCameraPosition,
+    AlongVelocity,
+}
+
+impl From<OrientDefinition> for OrientMode {
+    fn from(value: OrientDefinition) -> Self {
+        match value {
+            OrientDefinition::ParallelCameraDepthPlane => OrientMode::ParallelCameraDepthPlane,
+            OrientDefinition::FaceCameraPosition => OrientMode::FaceCameraPosition,
+            OrientDefinition::AlongVelocity => OrientMode::AlongVelocity,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+pub enum SimulationSpaceDefinition {
+    #[default]
+    Local,
+    Global,
+}
+
+impl From<SimulationSpaceDefinition> for SimulationSpace {
+    fn from(value: SimulationSpaceDefinition) -> Self {
+        match value {
+            SimulationSpaceDefinition::Local => SimulationSpace::Local,
+            SimulationSpaceDefinition::Global => SimulationSpace::Global,
+        }
+    }
+}
+
+impl EffectDefinition {
+    pub fn compile(&self) -> EffectAsset {
+        let mut color_gradient = Gradient::new();
+        for &(ratio, (r, g, b, a)) in &self.color {
+            color_gradient.add_key(ratio, Vec4::new(r, g, b, a));
+        }
+
+        let mut size_gradient = Gradient::new();
+        for &(ratio, size) in &self.size {
+            size_gradient.add_key(ratio, Vec3::splat(size));
+        }
+
+        let writer = ExprWriter::new();
+
+        let (age_min, age_max) = self.age;
+        let age = writer.lit(age_min).uniform(writer.lit(age_max)).expr();
+        let init_age = SetAttributeModifier::new(Attribute::AGE, age);
+
+        let (lifetime_mean, lifetime_deviation) = self.lifetime;
+        let mut lifetime = writer
+            .lit(lifetime_mean)
+            .normal(writer.lit(lifetime_deviation));
+        if let Some(name) = &self.lifetime_property {
+            lifetime = lifetime * writer.prop(writer.add_property(name, 1.0_f32.into()));
+        }
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime.expr());
+
+        // Tinted effects start out with the property color, the gradient then modulates it.
+        let init_color = self.color_property.as_ref().map(|name| {
+            let color = writer.add_property(name, Vec4::ONE.into());
+            SetAttributeModifier::new(Attribute::HDR_COLOR, writer.prop(color).expr())
+        });
+
+        let update_accel = self
+            .accel
+            .map(|(x, y, z)| AccelModifier::new(writer.lit(Vec3::new(x, y, z)).expr()));
+
+        let update_drag = self
+            .drag
+            .map(|drag| LinearDragModifier::new(writer.lit(drag).expr()));
+
+        let init_pos = SetPositionSphereModifier {
+            center: writer.lit(Vec3::ZERO).expr(),
+            radius: writer.lit(self.radius).expr(),
+            dimension: ShapeDimension::Volume,
+        };
+
+        // The velocity is random in any direction
+        let center = writer.attr(Attribute::POSITION);
+
+        let (speed_min, speed_max) = self.speed;
+        let speed = writer.lit(speed_min).uniform(writer.lit(speed_max));
+        let dir = writer
+            .rand(VectorType::VEC3F)
+            .mul(writer.lit(2.0))
+            .sub(writer.lit(1.0))
+            .normalized();
+        let init_vel =
+            SetAttributeModifier::new(Attribute::VELOCITY, (center + dir * speed).expr());
+
+        let round = self.round.then(|| RoundModifier {
+            roundness: writer.lit(1.0).expr(),
+        });
+
+        let orient = OrientModifier::new(self.orient.into());
+
+        let mut module = writer.finish();
+
+        let update_tangent_accel = self.tangent_accel.map(|tangent_accel| {
+            let (x, y, z) = tangent_accel.axis;
+            TangentAccelModifier::constant(
+                &mut module,
+                Vec3::ZERO,
+                Vec3::new(x, y, z),
+                tangent_accel.accel,
+            )
+        });
+
+        let mut effect = EffectAsset::new(self.capacity, self.spawner.settings(), module)
+            .with_name(self.name.clone())
+            .with_simulation_space(self.simulation_space.into())
+            .init(init_pos)
+            .init(init_vel)
+            .init(init_age)
+            .init(init_lifetime);
+
+        if let Some(init_color) = init_color {
+            effect = effect.init(init_color);
+        }
+
+        if let Some(update_drag) = update_drag {
+            effect = effect.update(update_drag);
+        }
+
+        if let Some(update_tangent_accel) = update_tangent_accel {
+            effect = effect.update(update_tangent_accel);
+        }
+
+        if let Some(update_accel) = update_accel {
+            effect = effect.update(update_accel);
+        }
+
+        if let Some(round) = round {
+            effect = effect.render(round);
+        }
+
+        effect
+            .render(ColorOverLifetimeModifier {
+                gradient: color_gradient,
+                blend: match self.color_property {
+                    Some(_) => ColorBlendMode::Modulate,
+                    None => ColorBlendMode::Overwrite,
+                },
+                mask: ColorBlendMask::RGBA,
+            })
+            .render(SizeOverLifetimeModifier {
+                gradient: size_gradient,
+                screen_space_size: false,
+            })
+            .render(orient)
+    }
+}
+
+#[derive(Default)]
+pub struct EffectDefinitionLoader;
+
+#[derive(Debug, Error)]
+pub enum EffectDefinitionLoaderError {
+    #[error("Could not read effect definition: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse effect definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for EffectDefinitionLoader {
+    type Asset = EffectDefinition;
+    type Settings = ();
+    type Error = EffectDefinitionLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        Ok(ron::de::from_bytes::<EffectDefinition>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effect.ron"]
+    }
+}
@@ -0,0 +1,160 @@
+mod definition;
+mod trail;
+
+pub use definition::*;
+pub use trail::*;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_fps_controller::controller::LogicalPlayer;
+use bevy_hanabi::*;
+
+use crate::{core::*, world::*};
+
+pub struct ParticlePlugin;
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((HanabiPlugin, TrailPlugin))
+            .init_asset::<EffectDefinition>()
+            .init_asset_loader::<EffectDefinitionLoader>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (compile_effects, checkpoint_fx, boost_fx, new_level_fx),
+            );
+    }
+}
+
+/// Maps each loaded [`EffectDefinition`] to the [`EffectAsset`] it is compiled into.
+#[derive(Resource, Default)]
+struct EffectDefinitions(HashMap<AssetId<EffectDefinition>, EffectDefinitionEntry>);
+
+struct EffectDefinitionEntry {
+    // Keeps the definition loaded (and watched for changes).
+    _definition: Handle<EffectDefinition>,
+    effect: Handle<EffectAsset>,
+}
+
+pub(crate) fn setup(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    let mut definitions = EffectDefinitions::default();
+
+    // Effects are compiled once their definition is loaded, until then the reserved handle stays empty.
+    let mut load = |name: String| -> Handle<EffectAsset> {
+        let definition: Handle<EffectDefinition> =
+            asset_server.load(format!("effects/{name}.effect.ron"));
+        let effect = effects.reserve_handle();
+
+        loading.0.push(definition.clone().into());
+        definitions.0.insert(
+            definition.id(),
+            EffectDefinitionEntry {
+                _definition: definition,
+                effect: effect.clone(),
+            },
+        );
+
+        effect
+    };
+
+    let checkpoint_fx: [Handle<EffectAsset>; LEVEL_COUNT] = (1..=LEVEL_COUNT)
+        .map(|x| load(format!("checkpoint_{x}")))
+        .collect::<Vec<Handle<EffectAsset>>>()
+        .try_into()
+        .unwrap();
+
+    let new_level_fx: [Handle<EffectAsset>; LEVEL_COUNT] = (1..=LEVEL_COUNT)
+        .map(|x| load(format!("new_level_{x}")))
+        .collect::<Vec<Handle<EffectAsset>>>()
+        .try_into()
+        .unwrap();
+
+    let fx = ParticleEffects {
+        boost_fx: load(String::from("boost")),
+        boost_idle_fx: load(String::from("boost_idle")),
+        player_boost_fx: load(String::from("player_boost")),
+        speed_trail_fx: load(String::from("speed_trail")),
+        new_level_fx,
+        checkpoint_fx,
+    };
+
+    cmd.insert_resource(fx);
+    cmd.insert_resource(definitions);
+}
+
+fn compile_effects(
+    mut er: EventReader<AssetEvent<EffectDefinition>>,
+    definitions: Res<Assets<EffectDefinition>>,
+    entries: Res<EffectDefinitions>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    for e in er.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = e else {
+            continue;
+        };
+
+        let (Some(definition), Some(entry)) = (definitions.get(*id), entries.0.get(id)) else {
+            continue;
+        };
+
+        effects.insert(&entry.effect, definition.compile());
+
+        info!("Compiled particle effect {}", definition.name);
+    }
+}
+
+fn checkpoint_fx(
+    mut er: EventReader<CheckpointReached>,
+    mut ew: EventWriter<PlayEffect>,
+    fx: Res<ParticleEffects>,
+    current_lvl: Res<CurrentLevel>,
+) {
+    for e in er.read() {
+        ew.write(PlayEffect {
+            effect: fx.get_checkpoint_fx(current_lvl.get()),
+            target: EffectTarget::Entity(e.entity),
+        });
+    }
+}
+
+fn boost_fx(
+    mut er: EventReader<Boosted>,
+    mut ew: EventWriter<PlayEffect>,
+    fx: Res<ParticleEffects>,
+    q_gtf: Query<&GlobalTransform>,
+) {
+    for e in er.read() {
+        let Ok(gtf) = q_gtf.get(e.boost) else {
+            continue;
+        };
+
+        ew.write(PlayEffect {
+            effect: fx.player_boost_fx.clone(),
+            target: EffectTarget::Entity(e.entity),
+        });
+
+        ew.write(PlayEffect {
+            effect: fx.boost_fx.clone(),
+            target: EffectTarget::Translation(gtf.translation()),
+        });
+    }
+}
+
+fn new_level_fx(
+    mut er: EventReader<LevelSpawned>,
+    mut ew: EventWriter<PlayEffect>,
+    fx: Res<ParticleEffects>,
+    q_player: Query<Entity, With<LogicalPlayer>>,
+) {
+    for LevelSpawned(level) in er.read() {
+        for player in &q_player {
+            ew.write(PlayEffect {
+                effect: fx.get_new_level_fx(*level),
+                target: EffectTarget::Entity(player),
+            });
+        }
+    }
+}
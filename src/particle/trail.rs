@@ -0,0 +1,78 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_fps_controller::controller::LogicalPlayer;
+use bevy_hanabi::*;
+
+use crate::{color::Resurrect64, core::*, player::PLAYER_HEIGHT};
+
+/// Horizontal speed at which the trail starts emitting.
+const TRAIL_MIN_SPEED: f32 = 12.;
+
+/// Horizontal speed at which the trail reaches its full rate and length.
+const TRAIL_MAX_SPEED: f32 = 60.;
+
+const TRAIL_MAX_RATE: f32 = 256.;
+
+/// Trail palette, the highest tier reached by the horizontal speed is used.
+const TRAIL_TIERS: [(f32, Color); 4] = [
+    (0., Resurrect64::GREEN),
+    (25., Resurrect64::TURQUOISE),
+    (40., Resurrect64::GOLD),
+    (55., Resurrect64::SCARLET),
+];
+
+/// A speed trail following its parent, driven by the parent's [`LinearVelocity`].
+#[derive(Component)]
+pub struct SpeedTrail;
+
+pub(super) struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_speed_trail, update_speed_trail).chain());
+    }
+}
+
+pub fn speed_trail(fx: &ParticleEffects) -> impl Bundle + use<> {
+    (
+        SpeedTrail,
+        ParticleEffect::new(fx.speed_trail_fx.clone()),
+        EffectProperties::default(),
+        Transform::from_translation(Vec3::NEG_Y * PLAYER_HEIGHT / 3.),
+        Visibility::Visible,
+    )
+}
+
+fn spawn_speed_trail(
+    mut cmd: Commands,
+    q_player: Query<Entity, Added<LogicalPlayer>>,
+    fx: Res<ParticleEffects>,
+) {
+    for player in &q_player {
+        cmd.entity(player).with_child(speed_trail(&fx));
+    }
+}
+
+fn update_speed_trail(
+    mut q_trail: Query<(&ChildOf, &mut EffectProperties, &mut EffectSpawner), With<SpeedTrail>>,
+    q_velocity: Query<&LinearVelocity>,
+) {
+    for (child_of, mut properties, mut spawner) in &mut q_trail {
+        let Ok(velocity) = q_velocity.get(child_of.parent()) else {
+            continue;
+        };
+
+        let speed = velocity.0.xz().length();
+        let t = ((speed - TRAIL_MIN_SPEED) / (TRAIL_MAX_SPEED - TRAIL_MIN_SPEED)).clamp(0., 1.);
+
+        let color = TRAIL_TIERS
+            .iter()
+            .rfind(|(threshold, _)| speed >= *threshold)
+            .map_or(TRAIL_TIERS[0].1, |(_, color)| *color);
+
+        properties.set("trail_color", color.to_linear().to_vec4().into());
+        properties.set("trail_length", (0.3 + t).into());
+
+        spawner.settings = SpawnerSettings::rate((t * TRAIL_MAX_RATE).into());
+    }
+}
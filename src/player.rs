@@ -4,19 +4,32 @@ use avian_pickup::actor::*;
 use avian3d::prelude::*;
 use bevy::{
     core_pipeline::{bloom::Bloom, tonemapping::Tonemapping},
+    input::InputSystem,
     pbr::{NotShadowCaster, NotShadowReceiver, VolumetricFog},
     prelude::*,
     render::{camera::Exposure, view::ColorGrading},
+    transform::TransformSystem,
 };
 use bevy_fps_controller::controller::*;
 
-use crate::core::*;
+use crate::{console::*, core::*, preset::*, style::*};
+
+pub const PLAYER_HEIGHT: f32 = 3.0;
+
+pub const SV_AIRACCELERATE: &str = "sv_airaccelerate";
+pub const SV_MAXAIRSPEED: &str = "sv_maxairspeed";
+pub const SV_AIRSPEEDCAP: &str = "sv_airspeedcap";
+pub const SV_FRICTION: &str = "sv_friction";
+pub const SV_NOCLIPSPEED: &str = "sv_noclipspeed";
+pub const SV_NOCLIPSPEED_FAST: &str = "sv_noclipspeed_fast";
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::InGame), setup)
+        app.add_plugins(MovementPlugin)
+            .add_systems(OnEnter(AppState::InGame), setup)
+            .add_systems(Update, detect_ground_surface.in_set(GameplaySet))
             .add_systems(
                 OnExit(AppState::InGame),
                 (cleanup::<LogicalPlayer>, cleanup::<RenderPlayer>),
@@ -24,44 +37,105 @@ impl Plugin for PlayerPlugin {
     }
 }
 
+/// The movement presets and cvars, applied to every [`FpsController`] including the ones simulated by the dedicated server.
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_cvar(
+            CVar::float(
+                SV_AIRACCELERATE,
+                MovementSettings::BEVY_HOP.air_acceleration,
+                "Acceleration while air strafing.",
+            )
+            .with_range(0., 1000.)
+            .archived()
+            .notify(),
+        )
+        .add_cvar(
+            CVar::float(
+                SV_MAXAIRSPEED,
+                MovementSettings::BEVY_HOP.max_air_speed,
+                "Maximum horizontal speed in the air.",
+            )
+            .with_range(0., 10000.)
+            .archived()
+            .notify(),
+        )
+        .add_cvar(
+            CVar::float(
+                SV_AIRSPEEDCAP,
+                MovementSettings::BEVY_HOP.air_speed_cap,
+                "Cap on the wish speed while in the air.",
+            )
+            .with_range(0., 1000.)
+            .archived()
+            .notify(),
+        )
+        .add_cvar(
+            CVar::float(
+                SV_FRICTION,
+                MovementSettings::BEVY_HOP.friction,
+                "Ground friction.",
+            )
+            .with_range(0., 100.)
+            .archived()
+            .notify(),
+        )
+        .add_cvar(
+            CVar::float(SV_NOCLIPSPEED, 50., "Speed while flying in noclip.")
+                .with_range(0., 1000.)
+                .cheat(),
+        )
+        .add_cvar(
+            CVar::float(
+                SV_NOCLIPSPEED_FAST,
+                100.,
+                "Speed while sprinting in noclip.",
+            )
+            .with_range(0., 1000.)
+            .cheat(),
+        )
+        .configure_sets(FixedUpdate, MovementSet.run_if(physics_running))
+        .add_systems(Update, apply_movement_settings)
+        // Instead of the `FpsControllerPlugin`, which moves once per frame.
+        .add_systems(
+            PreUpdate,
+            (fps_controller_input, fps_controller_look)
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            FixedUpdate,
+            (apply_style, fps_controller_look, fps_controller_move)
+                .chain()
+                .in_set(MovementSet),
+        )
+        .add_systems(
+            PostUpdate,
+            fps_controller_render.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// The controller moves once per fixed tick, so a tick of a client is a tick of the server simulating it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovementSet;
+
+/// Players stand still while the physics is paused or held by `phys_pause`.
+fn physics_running(time: Res<Time<Physics>>) -> bool {
+    !time.is_paused()
+}
+
 fn setup(mut cmd: Commands) {
     // Note that we have two entities for the player
     // One is a "logical" player that handles the physics computation and collision
     // The other is a "render" player that is what is displayed to the user
     // This distinction is useful for later on if you want to add multiplayer,
     // where often time these two ideas are not exactly synced up
-    let height = 3.0;
     let logical_entity = cmd
         .spawn((
-            Collider::cylinder(1.0, height),
-            (
-                Friction {
-                    dynamic_coefficient: 0.0,
-                    static_coefficient: 0.0,
-                    combine_rule: CoefficientCombine::Min,
-                },
-                Restitution {
-                    coefficient: 0.0,
-                    combine_rule: CoefficientCombine::Min,
-                },
-                LinearVelocity::ZERO,
-                TransformInterpolation,
-                RigidBody::Dynamic,
-                CollisionLayers::new(
-                    CollisionLayer::Player,
-                    [
-                        CollisionLayer::Default,
-                        CollisionLayer::Boost,
-                        CollisionLayer::Checkpoint,
-                        CollisionLayer::End,
-                    ],
-                ),
-                Sleeping,
-                LockedAxes::ROTATION_LOCKED,
-                Mass(1.0),
-                GravityScale(0.0),
-                Dominance(5),
-            ),
+            player_body(),
             (
                 Visibility::Visible,
                 Transform::from_translation(SPAWN_POINT),
@@ -74,17 +148,11 @@ fn setup(mut cmd: Commands) {
                     yaw: TAU * 5.0 / 8.0,
                     ..default()
                 },
-                FpsController {
-                    fly_speed: 50.,
-                    fast_fly_speed: 100.,
-                    air_acceleration: 20.,
-                    max_air_speed: 1000.,
-                    air_speed_cap: 10.,
-                    friction: 10.,
-                    ..default()
-                },
+                // Movement tuning is applied from the preset and cvars, see `apply_movement_settings`.
+                FpsController::default(),
             ),
-            CollisionEventsEnabled,
+            GroundSurface::default(),
+            (Style::default(), MovementPreset::default()),
         ))
         .insert(CameraConfig {
             height_offset: -0.5,
@@ -134,3 +202,101 @@ fn setup(mut cmd: Commands) {
         },
     ));
 }
+
+/// The physics body of a player, shared with the players simulated by the dedicated server.
+pub fn player_body() -> impl Bundle {
+    (
+        Collider::cylinder(1.0, PLAYER_HEIGHT),
+        Friction {
+            dynamic_coefficient: 0.0,
+            static_coefficient: 0.0,
+            combine_rule: CoefficientCombine::Min,
+        },
+        Restitution {
+            coefficient: 0.0,
+            combine_rule: CoefficientCombine::Min,
+        },
+        LinearVelocity::ZERO,
+        TransformInterpolation,
+        RigidBody::Dynamic,
+        CollisionLayers::new(
+            CollisionLayer::Player,
+            [
+                CollisionLayer::Default,
+                CollisionLayer::Boost,
+                CollisionLayer::Checkpoint,
+                CollisionLayer::End,
+            ],
+        ),
+        Sleeping,
+        LockedAxes::ROTATION_LOCKED,
+        Mass(1.0),
+        GravityScale(0.0),
+        Dominance(5),
+        CollisionEventsEnabled,
+    )
+}
+
+/// The surface the controller stands on, taken from the contacts of the player with the ground it has traction on.
+pub fn detect_ground_surface(
+    collisions: Collisions,
+    mut q_player: Query<(Entity, &FpsController, &mut GroundSurface)>,
+    q_surface: Query<&SurfaceMaterial>,
+) {
+    for (player, controller, mut ground) in &mut q_player {
+        if controller.ground_tick == 0 {
+            ground.0 = None;
+            continue;
+        }
+
+        let floor = collisions.collisions_with(player).find_map(|contact| {
+            // Manifold normals point from the first collider to the second.
+            let (other, up) = match contact.collider1 == player {
+                true => (contact.collider2, -1.),
+                false => (contact.collider1, 1.),
+            };
+
+            contact
+                .manifolds
+                .iter()
+                .any(|x| (x.normal * up).dot(Vec3::Y) > controller.traction_normal_cutoff)
+                .then_some(other)
+        });
+
+        // Ground without an authored surface falls back to the default material.
+        ground.0 = Some(
+            floor
+                .and_then(|x| q_surface.get(x).ok().copied())
+                .unwrap_or_default(),
+        );
+    }
+}
+
+/// Cvars changed from their default override the preset.
+fn apply_movement_settings(
+    cvars: Res<CVars>,
+    mut q_controller: Query<(&mut FpsController, Option<&MovementPreset>)>,
+    q_changed: Query<(), Or<(Added<FpsController>, Changed<MovementPreset>)>>,
+) {
+    if !cvars.is_changed() && q_changed.is_empty() {
+        return;
+    }
+
+    for (mut controller, preset) in &mut q_controller {
+        let settings = preset.copied().unwrap_or_default().settings();
+        let cvar = |name, preset| cvars.overridden(name).unwrap_or(preset);
+
+        controller.air_acceleration = cvar(SV_AIRACCELERATE, settings.air_acceleration);
+        controller.max_air_speed = cvar(SV_MAXAIRSPEED, settings.max_air_speed);
+        controller.air_speed_cap = cvar(SV_AIRSPEEDCAP, settings.air_speed_cap);
+        controller.friction = cvar(SV_FRICTION, settings.friction);
+        controller.acceleration = settings.acceleration;
+        controller.stop_speed = settings.stop_speed;
+        controller.jump_speed = settings.jump_speed;
+        controller.gravity = settings.gravity;
+        controller.walk_speed = settings.walk_speed;
+        controller.run_speed = settings.run_speed;
+        controller.fly_speed = cvars.float(SV_NOCLIPSPEED);
+        controller.fast_fly_speed = cvars.float(SV_NOCLIPSPEED_FAST);
+    }
+}
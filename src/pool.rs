@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{AudioSinkPlayback, PlaybackMode},
+    prelude::*,
+};
+use bevy_hanabi::{EffectSpawner, ParticleEffect};
+
+use crate::core::*;
+
+/// How long a pooled effect stays visible before its slot is released.
+const EFFECT_LIFETIME: f32 = 2.;
+
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OneShotBudget>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    (release_effects, play_effects, follow_targets).chain(),
+                    (release_sounds, play_sounds).chain(),
+                ),
+            );
+    }
+}
+
+/// The global budget of pooled one-shots, the pools are allocated once at startup.
+#[derive(Resource, Debug)]
+pub struct OneShotBudget {
+    pub effects: usize,
+    pub sounds: usize,
+    pub effect_overflow: Overflow,
+    pub sound_overflow: Overflow,
+}
+
+impl Default for OneShotBudget {
+    fn default() -> Self {
+        Self {
+            effects: 12,
+            sounds: 16,
+            effect_overflow: Overflow::StealOldest,
+            sound_overflow: Overflow::StealOldest,
+        }
+    }
+}
+
+/// What to do with a one-shot when every slot of its pool is in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Drop,
+    StealOldest,
+}
+
+#[derive(Component)]
+struct EffectSlot {
+    started: Option<Duration>,
+    follow: Option<Entity>,
+    lifetime: Timer,
+}
+
+impl Default for EffectSlot {
+    fn default() -> Self {
+        Self {
+            started: None,
+            follow: None,
+            lifetime: Timer::from_seconds(EFFECT_LIFETIME, TimerMode::Once),
+        }
+    }
+}
+
+#[derive(Component, Default)]
+struct SoundSlot {
+    started: Option<Duration>,
+}
+
+fn setup(mut cmd: Commands, budget: Res<OneShotBudget>) {
+    for _ in 0..budget.effects {
+        cmd.spawn((
+            EffectSlot::default(),
+            Transform::default(),
+            Visibility::Hidden,
+        ));
+    }
+
+    for _ in 0..budget.sounds {
+        cmd.spawn(SoundSlot::default());
+    }
+}
+
+/// Picks a free slot, or the one playing the longest if the overflow policy allows stealing.
+fn acquire(
+    slots: impl Iterator<Item = (Entity, Option<Duration>)>,
+    overflow: Overflow,
+) -> Option<Entity> {
+    let mut oldest: Option<(Entity, Duration)> = None;
+
+    for (entity, started) in slots {
+        let Some(started) = started else {
+            return Some(entity);
+        };
+
+        if oldest.is_none_or(|(_, x)| started < x) {
+            oldest = Some((entity, started));
+        }
+    }
+
+    match overflow {
+        Overflow::Drop => None,
+        Overflow::StealOldest => oldest.map(|(entity, _)| entity),
+    }
+}
+
+fn play_effects(
+    mut cmd: Commands,
+    mut er: EventReader<PlayEffect>,
+    mut q_slots: Query<(
+        Entity,
+        &mut EffectSlot,
+        &mut Transform,
+        &mut Visibility,
+        Option<&ParticleEffect>,
+        Option<&mut EffectSpawner>,
+    )>,
+    budget: Res<OneShotBudget>,
+    time: Res<Time>,
+) {
+    for e in er.read() {
+        let Some(slot) = acquire(
+            q_slots
+                .iter()
+                .map(|(entity, slot, ..)| (entity, slot.started)),
+            budget.effect_overflow,
+        ) else {
+            debug!("Effect budget exceeded, dropping one-shot effect.");
+            continue;
+        };
+
+        let Ok((entity, mut slot, mut transform, mut visibility, effect, spawner)) =
+            q_slots.get_mut(slot)
+        else {
+            continue;
+        };
+
+        slot.started = Some(time.elapsed());
+        slot.lifetime.reset();
+        slot.follow = match e.target {
+            EffectTarget::Entity(target) => Some(target),
+            EffectTarget::Translation(translation) => {
+                transform.translation = translation;
+                None
+            }
+        };
+
+        *visibility = Visibility::Visible;
+
+        // Restart the effect in place if the slot already holds it, otherwise swap it in.
+        match (effect, spawner) {
+            (Some(effect), Some(mut spawner)) if effect.handle == e.effect => spawner.reset(),
+            _ => {
+                cmd.entity(entity)
+                    .insert(ParticleEffect::new(e.effect.clone()));
+            }
+        }
+    }
+}
+
+fn follow_targets(
+    mut q_slots: Query<(&EffectSlot, &mut Transform)>,
+    q_gtf: Query<&GlobalTransform>,
+) {
+    for (slot, mut transform) in &mut q_slots {
+        let Some(target) = slot.follow else {
+            continue;
+        };
+
+        let Ok(gtf) = q_gtf.get(target) else {
+            continue;
+        };
+
+        transform.translation = gtf.translation();
+    }
+}
+
+fn release_effects(mut q_slots: Query<(&mut EffectSlot, &mut Visibility)>, t: Res<Time>) {
+    for (mut slot, mut visibility) in &mut q_slots {
+        if slot.started.is_none() {
+            continue;
+        }
+
+        slot.lifetime.tick(t.delta());
+        if !slot.lifetime.finished() {
+            continue;
+        }
+
+        slot.started = None;
+        slot.follow = None;
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn play_sounds(
+    mut cmd: Commands,
+    mut er: EventReader<PlaySound>,
+    mut q_slots: Query<(Entity, &mut SoundSlot, Option<&AudioSink>)>,
+    budget: Res<OneShotBudget>,
+    time: Res<Time>,
+) {
+    for e in er.read() {
+        let Some(slot) = acquire(
+            q_slots
+                .iter()
+                .map(|(entity, slot, _)| (entity, slot.started)),
+            budget.sound_overflow,
+        ) else {
+            debug!("Sound budget exceeded, dropping one-shot sound.");
+            continue;
+        };
+
+        let Ok((entity, mut slot, sink)) = q_slots.get_mut(slot) else {
+            continue;
+        };
+
+        if let Some(sink) = sink {
+            sink.stop();
+        }
+
+        slot.started = Some(time.elapsed());
+
+        // `PlaybackMode::Remove` strips the player once done, which frees the slot again.
+        cmd.entity(entity)
+            .remove::<(AudioPlayer, PlaybackSettings, AudioSink)>()
+            .insert((
+                AudioPlayer::new(e.sound.clone()),
+                PlaybackSettings {
+                    mode: PlaybackMode::Remove,
+                    volume: e.volume,
+                    speed: e.speed,
+                    ..default()
+                },
+            ));
+    }
+}
+
+fn release_sounds(mut q_slots: Query<&mut SoundSlot, Without<AudioPlayer>>) {
+    for mut slot in &mut q_slots {
+        slot.started = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slots() -> Vec<(Entity, Option<Duration>)> {
+        vec![
+            (Entity::from_raw(0), Some(Duration::from_secs(2))),
+            (Entity::from_raw(1), Some(Duration::from_secs(1))),
+            (Entity::from_raw(2), Some(Duration::from_secs(3))),
+        ]
+    }
+
+    #[test]
+    fn acquire_prefers_free_slots() {
+        let mut slots = slots();
+        slots.push((Entity::from_raw(3), None));
+
+        for overflow in [Overflow::Drop, Overflow::StealOldest] {
+            assert_eq!(
+                acquire(slots.iter().copied(), overflow),
+                Some(Entity::from_raw(3))
+            );
+        }
+    }
+
+    #[test]
+    fn acquire_overflow() {
+        assert_eq!(
+            acquire(slots().into_iter(), Overflow::StealOldest),
+            Some(Entity::from_raw(1))
+        );
+        assert_eq!(acquire(slots().into_iter(), Overflow::Drop), None);
+        assert_eq!(acquire(std::iter::empty(), Overflow::StealOldest), None);
+    }
+}
@@ -0,0 +1,293 @@
+mod snapshot;
+
+pub use snapshot::*;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::{console::*, core::*, integrity::*};
+
+pub const PRACTICE_KEEPVELOCITY: &str = "practice_keepvelocity";
+
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(SnapshotPlugin)
+            .init_resource::<SavedPositions>()
+            .add_cvar(
+                CVar::bool(
+                    PRACTICE_KEEPVELOCITY,
+                    false,
+                    "Restore the saved velocity when teleporting to a saved position.",
+                )
+                .archived(),
+            )
+            .add_console_command::<SavePosCommand, _>(savepos)
+            .add_console_command::<TeleportCommand, _>(tp)
+            .add_console_command::<PrevCpCommand, _>(prevcp)
+            .add_console_command::<NextCpCommand, _>(nextcp)
+            .add_console_command::<PracticeCommand, _>(practice)
+            .add_systems(Startup, default_aliases)
+            .add_systems(OnEnter(AppState::InGame), clear_positions)
+            .add_systems(Update, clear_positions.run_if(on_event::<SpawnLevel>))
+            .add_systems(PreUpdate, teleport.in_set(GameplaySet));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SavedPosition {
+    pub translation: Vec3,
+    pub velocity: Vec3,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+/// Positions saved with `savepos`, `current` is the one `tp` returns to.
+#[derive(Resource, Debug, Default)]
+pub struct SavedPositions {
+    pub positions: Vec<SavedPosition>,
+    pub current: usize,
+    /// Set by the teleport commands, applied before the controller moves.
+    pending: Option<SavedPosition>,
+}
+
+impl SavedPositions {
+    fn select(&mut self, index: usize) -> Option<usize> {
+        let position = *self.positions.get(index)?;
+        self.current = index;
+        self.pending = Some(position);
+        Some(index)
+    }
+}
+
+fn default_aliases(mut aliases: ResMut<Aliases>) {
+    aliases.insert_default("cp", "savepos");
+    aliases.insert_default("gocheck", "tp");
+}
+
+/// Saved positions only work in practice mode, the error is the reply otherwise.
+fn practicing(state: &State<PracticeState>) -> Result<(), &'static str> {
+    match state.get() {
+        PracticeState::On => Ok(()),
+        PracticeState::Off => {
+            Err("Saved positions need practice mode, turn it on with practice 1.")
+        }
+    }
+}
+
+fn clear_positions(mut positions: ResMut<SavedPositions>) {
+    *positions = SavedPositions::default();
+}
+
+fn teleport(
+    mut positions: ResMut<SavedPositions>,
+    mut q_player: Query<
+        (&mut Transform, &mut LinearVelocity, &mut FpsControllerInput),
+        With<LogicalPlayer>,
+    >,
+    cvars: Res<CVars>,
+) {
+    let Some(position) = positions.pending.take() else {
+        return;
+    };
+
+    for (mut transform, mut velocity, mut input) in &mut q_player {
+        transform.translation = position.translation;
+        velocity.0 = match cvars.bool(PRACTICE_KEEPVELOCITY) {
+            true => position.velocity,
+            false => Vec3::ZERO,
+        };
+        input.pitch = position.pitch;
+        input.yaw = position.yaw;
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "savepos")]
+struct SavePosCommand {}
+
+fn savepos(
+    mut log: ConsoleCommand<SavePosCommand>,
+    practice: Res<State<PracticeState>>,
+    mut positions: ResMut<SavedPositions>,
+    mut ew: EventWriter<InvalidateRun>,
+    q_player: Query<(&Transform, &LinearVelocity, &FpsControllerInput), With<LogicalPlayer>>,
+) {
+    let Some(Ok(SavePosCommand {})) = log.take() else {
+        return;
+    };
+
+    if let Err(e) = practicing(&practice) {
+        reply!(log, "{e}");
+        return;
+    }
+
+    let Ok((transform, velocity, input)) = q_player.single() else {
+        reply!(log, "Not in game!");
+        return;
+    };
+
+    positions.positions.push(SavedPosition {
+        translation: transform.translation,
+        velocity: velocity.0,
+        pitch: input.pitch,
+        yaw: input.yaw,
+    });
+    positions.current = positions.positions.len() - 1;
+
+    ew.write(InvalidateRun(Violation::Practice));
+
+    reply!(log, "Saved position #{}", positions.positions.len());
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "tp")]
+struct TeleportCommand {
+    /// Saved position to teleport to, defaults to the last one selected
+    #[arg(index = 1)]
+    position: Option<usize>,
+}
+
+fn tp(
+    mut log: ConsoleCommand<TeleportCommand>,
+    practice: Res<State<PracticeState>>,
+    mut positions: ResMut<SavedPositions>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(TeleportCommand { position })) = log.take() else {
+        return;
+    };
+
+    if let Err(e) = practicing(&practice) {
+        reply!(log, "{e}");
+        return;
+    }
+
+    if positions.positions.is_empty() {
+        reply!(log, "No saved position, use savepos first.");
+        return;
+    }
+
+    let index = match position {
+        Some(position) => position.checked_sub(1),
+        None => Some(positions.current),
+    };
+
+    match index.and_then(|x| positions.select(x)) {
+        Some(_) => {
+            ew.write(InvalidateRun(Violation::Practice));
+        }
+        None => reply!(
+            log,
+            "No position #{}, saved positions are #1 to #{}.",
+            position.unwrap_or_default(),
+            positions.positions.len()
+        ),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "prevcp")]
+struct PrevCpCommand {}
+
+fn prevcp(
+    mut log: ConsoleCommand<PrevCpCommand>,
+    practice: Res<State<PracticeState>>,
+    mut positions: ResMut<SavedPositions>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(PrevCpCommand {})) = log.take() else {
+        return;
+    };
+
+    if let Err(e) = practicing(&practice) {
+        reply!(log, "{e}");
+        return;
+    }
+
+    let index = positions.current.saturating_sub(1);
+
+    match positions.select(index) {
+        Some(x) => {
+            ew.write(InvalidateRun(Violation::Practice));
+            reply!(log, "Position #{}", x + 1);
+        }
+        None => reply!(log, "No saved position, use savepos first."),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "nextcp")]
+struct NextCpCommand {}
+
+fn nextcp(
+    mut log: ConsoleCommand<NextCpCommand>,
+    practice: Res<State<PracticeState>>,
+    mut positions: ResMut<SavedPositions>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(NextCpCommand {})) = log.take() else {
+        return;
+    };
+
+    if let Err(e) = practicing(&practice) {
+        reply!(log, "{e}");
+        return;
+    }
+
+    let index = (positions.current + 1).min(positions.positions.len().saturating_sub(1));
+
+    match positions.select(index) {
+        Some(x) => {
+            ew.write(InvalidateRun(Violation::Practice));
+            reply!(log, "Position #{}", x + 1);
+        }
+        None => reply!(log, "No saved position, use savepos first."),
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "practice")]
+struct PracticeCommand {
+    #[arg(value_parser = clap::builder::BoolishValueParser::new())]
+    enabled: Option<bool>,
+}
+
+fn practice(
+    mut log: ConsoleCommand<PracticeCommand>,
+    state: Res<State<PracticeState>>,
+    mut ns: ResMut<NextState<PracticeState>>,
+    app_state: Res<State<AppState>>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(PracticeCommand { enabled })) = log.take() else {
+        return;
+    };
+
+    let current = *state.get() == PracticeState::On;
+
+    let Some(enabled) = enabled else {
+        reply!(log, "practice: {}", current as i32);
+        return;
+    };
+
+    if current == enabled {
+        return;
+    }
+
+    ns.set(match enabled {
+        true => PracticeState::On,
+        false => PracticeState::Off,
+    });
+
+    // The run so far may have been rewound.
+    if enabled && *app_state.get() == AppState::InGame {
+        ew.write(InvalidateRun(Violation::Practice));
+    }
+
+    reply!(log, "practice: {}", enabled as i32);
+}
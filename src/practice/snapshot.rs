@@ -0,0 +1,281 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, time::Stopwatch};
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::{core::*, duration::*, integrity::*};
+
+/// How far back holding the rewind key goes.
+const REWIND_SECONDS: f32 = 5.;
+
+const REWIND_KEY: KeyCode = KeyCode::KeyT;
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveStates>()
+            .init_resource::<RewindBuffer>()
+            .add_console_command::<SaveStateCommand, _>(savestate)
+            .add_console_command::<LoadStateCommand, _>(loadstate)
+            .add_systems(OnEnter(AppState::InGame), clear_snapshots)
+            .add_systems(Update, clear_snapshots.run_if(on_event::<SpawnLevel>))
+            .add_systems(
+                FixedUpdate,
+                (rewind, record_rewind)
+                    .chain()
+                    .in_set(GameplaySet)
+                    .run_if(in_state(ChatState::Closed))
+                    .run_if(in_state(PracticeState::On)),
+            );
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PlayerSnapshot {
+    transform: Transform,
+    velocity: Vec3,
+    ground_tick: u8,
+    noclip: bool,
+    pitch: f32,
+    yaw: f32,
+}
+
+#[derive(Debug, Clone)]
+struct PropSnapshot {
+    entity: Entity,
+    transform: Transform,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+}
+
+/// Everything needed to put the current level back into an earlier state.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    player: Option<PlayerSnapshot>,
+    props: Vec<PropSnapshot>,
+    history: Vec<Entity>,
+    level_duration: Stopwatch,
+    run_results: [Duration; LEVEL_COUNT],
+}
+
+/// Named snapshots taken with `savestate`.
+#[derive(Resource, Default)]
+pub struct SaveStates(BTreeMap<String, Snapshot>);
+
+/// Rolling snapshots of the last few seconds, one per fixed tick.
+#[derive(Resource, Default)]
+pub struct RewindBuffer(VecDeque<Snapshot>);
+
+#[derive(SystemParam)]
+pub struct SnapshotParams<'w, 's> {
+    q_player: Query<
+        'w,
+        's,
+        (
+            &'static mut Transform,
+            &'static mut LinearVelocity,
+            &'static mut FpsController,
+            &'static mut FpsControllerInput,
+        ),
+        (With<LogicalPlayer>, Without<Prop>),
+    >,
+    q_props: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Transform,
+            &'static mut LinearVelocity,
+            &'static mut AngularVelocity,
+        ),
+        (With<Prop>, Without<LogicalPlayer>),
+    >,
+    history: ResMut<'w, History>,
+    level_duration: ResMut<'w, LevelDuration>,
+    run_duration: ResMut<'w, RunDuration>,
+}
+
+impl SnapshotParams<'_, '_> {
+    pub fn capture(&self) -> Snapshot {
+        let player = self
+            .q_player
+            .single()
+            .ok()
+            .map(|(transform, velocity, controller, input)| PlayerSnapshot {
+                transform: *transform,
+                velocity: velocity.0,
+                ground_tick: controller.ground_tick,
+                noclip: matches!(controller.move_mode, MoveMode::Noclip),
+                pitch: input.pitch,
+                yaw: input.yaw,
+            });
+
+        let props = self
+            .q_props
+            .iter()
+            .map(
+                |(entity, transform, linear_velocity, angular_velocity)| PropSnapshot {
+                    entity,
+                    transform: *transform,
+                    linear_velocity: linear_velocity.0,
+                    angular_velocity: angular_velocity.0,
+                },
+            )
+            .collect();
+
+        Snapshot {
+            player,
+            props,
+            history: self.history.0.clone(),
+            level_duration: self.level_duration.0.clone(),
+            run_results: self.run_duration.results,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        if let (Some(player), Ok((mut transform, mut velocity, mut controller, mut input))) =
+            (&snapshot.player, self.q_player.single_mut())
+        {
+            *transform = player.transform;
+            velocity.0 = player.velocity;
+            controller.ground_tick = player.ground_tick;
+            controller.move_mode = match player.noclip {
+                true => MoveMode::Noclip,
+                false => MoveMode::Ground,
+            };
+            input.pitch = player.pitch;
+            input.yaw = player.yaw;
+        }
+
+        for prop in &snapshot.props {
+            // Props can be gone, e.g. after the level was reloaded.
+            let Ok((_, mut transform, mut linear_velocity, mut angular_velocity)) =
+                self.q_props.get_mut(prop.entity)
+            else {
+                continue;
+            };
+
+            *transform = prop.transform;
+            linear_velocity.0 = prop.linear_velocity;
+            angular_velocity.0 = prop.angular_velocity;
+        }
+
+        self.history.0.clone_from(&snapshot.history);
+        self.level_duration.0 = snapshot.level_duration.clone();
+        self.run_duration.results = snapshot.run_results;
+    }
+}
+
+fn clear_snapshots(mut save_states: ResMut<SaveStates>, mut rewind: ResMut<RewindBuffer>) {
+    save_states.0.clear();
+    rewind.0.clear();
+}
+
+/// Typing into the console doesn't rewind.
+fn rewinding(keys: &ButtonInput<KeyCode>, console_open: &ConsoleOpen) -> bool {
+    !console_open.open && keys.pressed(REWIND_KEY)
+}
+
+fn record_rewind(
+    mut buffer: ResMut<RewindBuffer>,
+    keys: Res<ButtonInput<KeyCode>>,
+    console_open: Res<ConsoleOpen>,
+    params: SnapshotParams,
+    time: Res<Time<Fixed>>,
+) {
+    if rewinding(&keys, &console_open) {
+        return;
+    }
+
+    let capacity = (REWIND_SECONDS / time.timestep().as_secs_f32()) as usize;
+
+    while buffer.0.len() >= capacity {
+        buffer.0.pop_front();
+    }
+
+    buffer.0.push_back(params.capture());
+}
+
+fn rewind(
+    mut buffer: ResMut<RewindBuffer>,
+    keys: Res<ButtonInput<KeyCode>>,
+    console_open: Res<ConsoleOpen>,
+    mut params: SnapshotParams,
+    mut ew: EventWriter<InvalidateRun>,
+    mut rewound: Local<bool>,
+) {
+    let started = !*rewound;
+    *rewound = rewinding(&keys, &console_open);
+
+    if !*rewound {
+        return;
+    }
+
+    // Keep the oldest snapshot around, rewinding stops there.
+    let snapshot = match buffer.0.len() {
+        0 => return,
+        1 => buffer.0[0].clone(),
+        _ => buffer.0.pop_back().unwrap(),
+    };
+
+    params.restore(&snapshot);
+
+    if started {
+        ew.write(InvalidateRun(Violation::Practice));
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "savestate")]
+struct SaveStateCommand {
+    #[arg(index = 1, default_value = "quick")]
+    name: String,
+}
+
+fn savestate(
+    mut log: ConsoleCommand<SaveStateCommand>,
+    mut save_states: ResMut<SaveStates>,
+    params: SnapshotParams,
+) {
+    let Some(Ok(SaveStateCommand { name })) = log.take() else {
+        return;
+    };
+
+    save_states.0.insert(name.clone(), params.capture());
+
+    reply!(log, "Saved state {name}");
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "loadstate")]
+struct LoadStateCommand {
+    #[arg(index = 1, default_value = "quick")]
+    name: String,
+}
+
+fn loadstate(
+    mut log: ConsoleCommand<LoadStateCommand>,
+    save_states: Res<SaveStates>,
+    mut params: SnapshotParams,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(LoadStateCommand { name })) = log.take() else {
+        return;
+    };
+
+    let Some(snapshot) = save_states.0.get(&name) else {
+        reply!(log, "No saved state {name}");
+        return;
+    };
+
+    params.restore(snapshot);
+
+    ew.write(InvalidateRun(Violation::Practice));
+}
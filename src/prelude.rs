@@ -1,11 +1,24 @@
 pub use crate::audio::*;
+pub use crate::chat::*;
 pub use crate::color::*;
 pub use crate::console::*;
 pub use crate::core::*;
 pub use crate::duration::*;
 pub use crate::input::*;
+pub use crate::input_display::*;
+pub use crate::integrity::*;
+pub use crate::jumpstats::*;
 pub use crate::loading::*;
+pub use crate::mouse::*;
+pub use crate::net::*;
 pub use crate::particle::*;
 pub use crate::player::*;
+pub use crate::pool::*;
+pub use crate::practice::*;
+pub use crate::preset::*;
+pub use crate::records::*;
+pub use crate::spectator::*;
+pub use crate::strafe_trainer::*;
+pub use crate::style::*;
 pub use crate::ui::*;
 pub use crate::world::*;
@@ -0,0 +1,346 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Source units per unit of this game, a 72 units tall player is as tall as ours.
+const SOURCE_UNITS: f32 = 24.;
+
+/// Selecting a preset for the local player, the settings are applied by the `MovementPlugin`.
+pub struct PresetPlugin;
+
+impl Plugin for PresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<MovementPresetCommand, _>(movement_preset)
+            .add_console_command::<TickRateCommand, _>(tickrate)
+            .add_systems(
+                Update,
+                (
+                    sync_preset.in_set(GameplaySet),
+                    apply_tick_rate.run_if(resource_changed::<RunCategory>),
+                ),
+            );
+    }
+}
+
+/// Movement physics modeled on other games, runs are ranked per preset.
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    ValueEnum,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum MovementPreset {
+    #[default]
+    #[value(name = "bevyhop")]
+    BevyHop,
+    #[value(name = "cs16")]
+    Cs16,
+    #[value(name = "css")]
+    Css,
+    #[value(name = "csgo")]
+    Csgo,
+}
+
+impl std::fmt::Display for MovementPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovementPreset::BevyHop => write!(f, "Bevy Hop"),
+            MovementPreset::Cs16 => write!(f, "CS 1.6"),
+            MovementPreset::Css => write!(f, "CS:S"),
+            MovementPreset::Csgo => write!(f, "CS:GO"),
+        }
+    }
+}
+
+/// Rate of the fixed timestep physics and movement run at, runs are ranked per tick rate.
+#[derive(
+    Serialize,
+    Deserialize,
+    ValueEnum,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum TickRate {
+    #[value(name = "64")]
+    Tick64,
+    /// The 15ms ticks of Source, 66.67 Hz.
+    #[value(name = "66")]
+    Tick66,
+    #[value(name = "100")]
+    Tick100,
+    #[default]
+    #[value(name = "128")]
+    Tick128,
+}
+
+impl std::fmt::Display for TickRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TickRate::Tick64 => write!(f, "64 tick"),
+            TickRate::Tick66 => write!(f, "66 tick"),
+            TickRate::Tick100 => write!(f, "100 tick"),
+            TickRate::Tick128 => write!(f, "128 tick"),
+        }
+    }
+}
+
+impl TickRate {
+    pub fn timestep(&self) -> Duration {
+        match self {
+            TickRate::Tick64 => Duration::from_micros(15625),
+            TickRate::Tick66 => Duration::from_millis(15),
+            TickRate::Tick100 => Duration::from_millis(10),
+            TickRate::Tick128 => Duration::from_nanos(7_812_500),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let tick_rates = Self::value_variants();
+        let i = tick_rates
+            .iter()
+            .position(|x| x == self)
+            .unwrap_or_default();
+
+        tick_rates[(i + 1) % tick_rates.len()]
+    }
+}
+
+/// The [`FpsController`] fields a preset sets, plus the tick rate it is played at by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovementSettings {
+    pub air_acceleration: f32,
+    pub max_air_speed: f32,
+    pub air_speed_cap: f32,
+    pub acceleration: f32,
+    pub friction: f32,
+    pub stop_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub walk_speed: f32,
+    pub run_speed: f32,
+    pub tick_rate: TickRate,
+}
+
+impl MovementSettings {
+    pub const BEVY_HOP: Self = Self {
+        air_acceleration: 20.,
+        max_air_speed: 1000.,
+        air_speed_cap: 10.,
+        acceleration: 10.,
+        friction: 10.,
+        stop_speed: 1.,
+        jump_speed: 8.5,
+        gravity: 23.,
+        walk_speed: 9.,
+        run_speed: 14.,
+        tick_rate: TickRate::Tick128,
+    };
+
+    /// The shared Goldsrc/Source values, 250 ups running with an air wish speed capped at 30 ups.
+    const SOURCE: Self = Self {
+        air_acceleration: 10.,
+        max_air_speed: 1000.,
+        air_speed_cap: 30. / SOURCE_UNITS,
+        acceleration: 5.,
+        friction: 4.,
+        stop_speed: 100. / SOURCE_UNITS,
+        jump_speed: 268.3 / SOURCE_UNITS,
+        gravity: 800. / SOURCE_UNITS,
+        walk_speed: 250. / SOURCE_UNITS,
+        // There is no sprint, the walk key only slows down.
+        run_speed: 250. / SOURCE_UNITS,
+        tick_rate: TickRate::Tick66,
+    };
+
+    pub const CS16: Self = Self {
+        stop_speed: 75. / SOURCE_UNITS,
+        tick_rate: TickRate::Tick100,
+        ..Self::SOURCE
+    };
+
+    pub const CSS: Self = Self::SOURCE;
+
+    pub const CSGO: Self = Self {
+        air_acceleration: 12.,
+        acceleration: 5.5,
+        friction: 5.2,
+        stop_speed: 80. / SOURCE_UNITS,
+        jump_speed: 301.99 / SOURCE_UNITS,
+        tick_rate: TickRate::Tick64,
+        ..Self::SOURCE
+    };
+}
+
+impl MovementPreset {
+    pub fn settings(&self) -> MovementSettings {
+        match self {
+            MovementPreset::BevyHop => MovementSettings::BEVY_HOP,
+            MovementPreset::Cs16 => MovementSettings::CS16,
+            MovementPreset::Css => MovementSettings::CSS,
+            MovementPreset::Csgo => MovementSettings::CSGO,
+        }
+    }
+
+    /// The next preset, for cycling through them in menus.
+    pub fn next(&self) -> Self {
+        let presets = Self::value_variants();
+        let i = presets.iter().position(|x| x == self).unwrap_or_default();
+
+        presets[(i + 1) % presets.len()]
+    }
+}
+
+fn sync_preset(
+    category: Res<RunCategory>,
+    mut q_preset: Query<&mut MovementPreset, With<LogicalPlayer>>,
+) {
+    for mut preset in &mut q_preset {
+        preset.set_if_neq(category.preset);
+    }
+}
+
+/// Applied as soon as it is chosen, the controller moves once per fixed tick so the run plays at the rate it is ranked with.
+fn apply_tick_rate(category: Res<RunCategory>, mut time: ResMut<Time<Fixed>>) {
+    let timestep = category.tick_rate.timestep();
+
+    if time.timestep() != timestep {
+        time.set_timestep(timestep);
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "movement_preset")]
+struct MovementPresetCommand {
+    preset: Option<MovementPreset>,
+}
+
+fn movement_preset(
+    mut log: ConsoleCommand<MovementPresetCommand>,
+    mut category: ResMut<RunCategory>,
+    app_state: Res<State<AppState>>,
+    client: Option<Res<Client>>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(MovementPresetCommand { preset })) = log.take() else {
+        return;
+    };
+
+    let Some(preset) = preset else {
+        let settings = category.preset.settings();
+
+        reply!(log, "movement_preset: {} ({settings:?})", category.preset);
+        return;
+    };
+
+    if category.preset == preset {
+        return;
+    }
+
+    // The tick rate of the server is kept while connected.
+    match client {
+        Some(_) => category.preset = preset,
+        None => category.set_preset(preset),
+    }
+
+    // The run so far was played with other physics.
+    if *app_state.get() == AppState::InGame {
+        ew.write(InvalidateRun(Violation::CategoryChanged));
+    }
+
+    reply!(log, "movement_preset: {preset} ({})", category.tick_rate);
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "tickrate")]
+struct TickRateCommand {
+    tick_rate: Option<TickRate>,
+}
+
+fn tickrate(
+    mut log: ConsoleCommand<TickRateCommand>,
+    mut category: ResMut<RunCategory>,
+    app_state: Res<State<AppState>>,
+    client: Option<Res<Client>>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(TickRateCommand { tick_rate })) = log.take() else {
+        return;
+    };
+
+    let Some(tick_rate) = tick_rate else {
+        reply!(log, "tickrate: {}", category.tick_rate);
+        return;
+    };
+
+    if category.tick_rate == tick_rate {
+        return;
+    }
+
+    // Servers simulate every player at their own tick rate.
+    if client.is_some() {
+        reply!(
+            log,
+            "Can't change the tick rate while connected to a server."
+        );
+        return;
+    }
+
+    category.tick_rate = tick_rate;
+
+    if *app_state.get() == AppState::InGame {
+        ew.write(InvalidateRun(Violation::CategoryChanged));
+    }
+
+    reply!(log, "tickrate: {tick_rate}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestep_matches_rate() {
+        for (tick_rate, hz) in [
+            (TickRate::Tick64, 64.),
+            (TickRate::Tick66, 200. / 3.),
+            (TickRate::Tick100, 100.),
+            (TickRate::Tick128, 128.),
+        ] {
+            assert!((tick_rate.timestep().as_secs_f64() * hz - 1.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn next_cycles_through_all() {
+        let mut tick_rate = TickRate::default();
+
+        for _ in TickRate::value_variants() {
+            tick_rate = tick_rate.next();
+        }
+
+        assert_eq!(tick_rate, TickRate::default());
+        assert_eq!(TickRate::Tick128.next(), TickRate::Tick64);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{core::*, duration::*, integrity::*, preset::*, style::*};
+
+/// Personal bests are stored next to the console config.
+#[cfg(not(target_arch = "wasm32"))]
+const RECORDS_FILE: &str = "records.ron";
+
+pub struct RecordsPlugin;
+
+impl Plugin for RecordsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersonalBests>()
+            .init_resource::<RunCategory>()
+            .add_systems(Startup, load_personal_bests)
+            .add_systems(OnEnter(AppState::GameOver), update_personal_bests);
+    }
+}
+
+/// The settings a run is played with, records are kept separately for each category.
+#[derive(
+    Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(default)]
+pub struct RunCategory {
+    pub preset: MovementPreset,
+    pub tick_rate: TickRate,
+    pub style: Style,
+    /// Holding jump keeps jumping, scroll runs need a new press for every jump.
+    pub auto_bhop: bool,
+}
+
+impl Default for RunCategory {
+    fn default() -> Self {
+        Self {
+            preset: default(),
+            tick_rate: default(),
+            style: default(),
+            auto_bhop: true,
+        }
+    }
+}
+
+impl RunCategory {
+    /// Switches to the tick rate of the preset as well, it can be changed on its own afterwards.
+    pub fn set_preset(&mut self, preset: MovementPreset) {
+        self.preset = preset;
+        self.tick_rate = preset.settings().tick_rate;
+    }
+}
+
+impl std::fmt::Display for RunCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}, {}, ", self.preset, self.tick_rate, self.style)?;
+
+        match self.auto_bhop {
+            true => write!(f, "Auto"),
+            false => write!(f, "Scroll"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Bests {
+    pub run: Option<Duration>,
+    pub levels: [Option<Duration>; LEVEL_COUNT],
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct PersonalBests {
+    pub categories: BTreeMap<RunCategory, Bests>,
+    /// Whether the last finished run beat the previous best.
+    #[serde(skip)]
+    pub improved: bool,
+}
+
+impl PersonalBests {
+    pub fn get(&self, category: &RunCategory) -> Option<&Bests> {
+        self.categories.get(category)
+    }
+
+    fn submit(&mut self, category: RunCategory, run_duration: &RunDuration) {
+        let bests = self.categories.entry(category).or_default();
+        let run = run_duration.results.iter().sum::<Duration>();
+
+        self.improved = bests.run.is_none_or(|x| run < x);
+        if self.improved {
+            bests.run = Some(run);
+        }
+
+        for (best, result) in bests.levels.iter_mut().zip(run_duration.results) {
+            if best.is_none_or(|x| result < x) {
+                *best = Some(result);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn records_path() -> std::path::PathBuf {
+    std::path::Path::new(crate::console::CONFIG_DIR).join(RECORDS_FILE)
+}
+
+fn load_personal_bests(mut personal_bests: ResMut<PersonalBests>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match std::fs::read_to_string(records_path()) {
+        Ok(records) => match ron::from_str(&records) {
+            Ok(records) => *personal_bests = records,
+            Err(e) => warn!("Could not parse {RECORDS_FILE}: {e}"),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Could not read {RECORDS_FILE}: {e}"),
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = personal_bests;
+}
+
+pub fn update_personal_bests(
+    mut personal_bests: ResMut<PersonalBests>,
+    run_duration: Res<RunDuration>,
+    integrity: Res<RunIntegrity>,
+    category: Res<RunCategory>,
+) {
+    if !integrity.is_valid() {
+        personal_bests.improved = false;
+        return;
+    }
+
+    personal_bests.submit(*category, &run_duration);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = ron::ser::to_string_pretty(&*personal_bests, default())
+        .map_err(std::io::Error::other)
+        .and_then(|x| {
+            std::fs::create_dir_all(crate::console::CONFIG_DIR)?;
+            std::fs::write(records_path(), x)
+        })
+    {
+        warn!("Could not write {RECORDS_FILE}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(levels: [u64; LEVEL_COUNT]) -> RunDuration {
+        RunDuration {
+            results: levels.map(Duration::from_secs),
+        }
+    }
+
+    #[test]
+    fn submit_keeps_the_best_run_and_levels() {
+        let mut personal_bests = PersonalBests::default();
+        let category = RunCategory::default();
+
+        personal_bests.submit(category, &run([10, 20, 30]));
+        assert!(personal_bests.improved);
+
+        personal_bests.submit(category, &run([5, 30, 40]));
+        assert!(!personal_bests.improved);
+
+        let bests = personal_bests.get(&category).unwrap();
+        assert_eq!(bests.run, Some(Duration::from_secs(60)));
+        assert_eq!(
+            bests.levels,
+            [5, 20, 30].map(|x| Some(Duration::from_secs(x)))
+        );
+
+        personal_bests.submit(category, &run([10, 20, 20]));
+        assert!(personal_bests.improved);
+        assert_eq!(
+            personal_bests.get(&category).unwrap().run,
+            Some(Duration::from_secs(50))
+        );
+    }
+
+    #[test]
+    fn submit_per_category() {
+        let mut personal_bests = PersonalBests::default();
+        let category = RunCategory::default();
+        let mut sideways = category;
+        sideways.style = Style::Sideways;
+
+        personal_bests.submit(category, &run([1, 1, 1]));
+        personal_bests.submit(sideways, &run([9, 9, 9]));
+        assert!(personal_bests.improved);
+
+        assert_eq!(
+            personal_bests.get(&category).unwrap().run,
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            personal_bests.get(&sideways).unwrap().run,
+            Some(Duration::from_secs(27))
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::Parser;
+
+use crate::{core::*, mouse::*, net::*, player::*};
+
+/// Height of the eyes above the center of a player.
+const EYE_HEIGHT: f32 = PLAYER_HEIGHT / 2. - 0.5;
+
+const CHASE_DISTANCE: f32 = 8.;
+const CHASE_HEIGHT: f32 = 3.;
+
+const FREE_SPEED: f32 = 30.;
+const FREE_SPEED_FAST: f32 = 90.;
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<SpectatorState>()
+            .init_resource::<Spectator>()
+            .add_console_command::<SpectateCommand, _>(spectate)
+            .add_systems(OnEnter(SpectatorState::Spectating), detach_camera)
+            .add_systems(OnExit(SpectatorState::Spectating), attach_camera)
+            .add_systems(OnEnter(RaceState::Watching), start_spectating)
+            .add_systems(OnEnter(RaceState::Countdown), stop_spectating)
+            .add_systems(OnExit(AppState::InGame), stop_spectating)
+            .add_systems(
+                PreUpdate,
+                hold_player
+                    .after(fps_controller_input)
+                    .run_if(in_state(SpectatorState::Spectating)),
+            )
+            .add_systems(
+                Update,
+                (spectator_controls, spectator_look)
+                    .in_set(GameplaySet)
+                    .run_if(in_state(SpectatorState::Spectating).and(in_state(ChatState::Closed))),
+            )
+            .add_systems(
+                PostUpdate,
+                // After the remote players are interpolated for this frame.
+                move_spectator_camera
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(SpectatorState::Spectating)),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpectatorState {
+    #[default]
+    Off,
+    Spectating,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpectatorMode {
+    #[default]
+    FirstPerson,
+    Chase,
+    Free,
+}
+
+impl SpectatorMode {
+    fn next(self) -> Self {
+        match self {
+            SpectatorMode::FirstPerson => SpectatorMode::Chase,
+            SpectatorMode::Chase => SpectatorMode::Free,
+            SpectatorMode::Free => SpectatorMode::FirstPerson,
+        }
+    }
+}
+
+impl std::fmt::Display for SpectatorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpectatorMode::FirstPerson => write!(f, "First person"),
+            SpectatorMode::Chase => write!(f, "Chase"),
+            SpectatorMode::Free => write!(f, "Free"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Spectator {
+    pub mode: SpectatorMode,
+    /// The followed player, the free camera ignores it.
+    pub target: Option<PlayerId>,
+    yaw: f32,
+    pitch: f32,
+}
+
+/// Replaces [`RenderPlayer`] on the camera while spectating, to put it back afterwards.
+#[derive(Component)]
+struct SpectatorCamera {
+    logical_entity: Entity,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "spectate")]
+struct SpectateCommand {}
+
+fn spectate(
+    mut log: ConsoleCommand<SpectateCommand>,
+    app_state: Res<State<AppState>>,
+    state: Res<State<SpectatorState>>,
+    mut ns: ResMut<NextState<SpectatorState>>,
+) {
+    let Some(Ok(SpectateCommand {})) = log.take() else {
+        return;
+    };
+
+    if *app_state.get() != AppState::InGame {
+        reply!(log, "Spectating is only possible in game.");
+        return;
+    }
+
+    ns.set(match state.get() {
+        SpectatorState::Off => SpectatorState::Spectating,
+        SpectatorState::Spectating => SpectatorState::Off,
+    });
+}
+
+fn start_spectating(mut ns: ResMut<NextState<SpectatorState>>) {
+    ns.set(SpectatorState::Spectating);
+}
+
+fn stop_spectating(mut ns: ResMut<NextState<SpectatorState>>) {
+    ns.set(SpectatorState::Off);
+}
+
+fn detach_camera(
+    mut cmd: Commands,
+    mut spectator: ResMut<Spectator>,
+    q_camera: Query<(Entity, &RenderPlayer, &Transform)>,
+    q_remote: Query<(&RemotePlayer, &Visibility)>,
+) {
+    for (entity, render_player, transform) in &q_camera {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+
+        spectator.yaw = yaw;
+        spectator.pitch = pitch;
+
+        cmd.entity(entity)
+            .remove::<RenderPlayer>()
+            .insert(SpectatorCamera {
+                logical_entity: render_player.logical_entity,
+            });
+    }
+
+    spectator.target = targets(&q_remote).first().copied();
+
+    if spectator.target.is_none() {
+        spectator.mode = SpectatorMode::Free;
+    }
+}
+
+fn attach_camera(mut cmd: Commands, q_camera: Query<(Entity, &SpectatorCamera)>) {
+    for (entity, camera) in &q_camera {
+        cmd.entity(entity)
+            .remove::<SpectatorCamera>()
+            .insert(RenderPlayer {
+                logical_entity: camera.logical_entity,
+            });
+    }
+}
+
+/// The players that can be followed, on the same level as the spectator.
+fn targets(q_remote: &Query<(&RemotePlayer, &Visibility)>) -> Vec<PlayerId> {
+    let mut ids = q_remote
+        .iter()
+        .filter(|(_, visibility)| **visibility != Visibility::Hidden)
+        .map(|(x, _)| x.id)
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+/// The local player stands still while its owner is watching.
+fn hold_player(mut q_input: Query<&mut FpsControllerInput, With<LogicalPlayer>>) {
+    for mut input in &mut q_input {
+        input.movement = Vec3::ZERO;
+        input.jump = false;
+    }
+}
+
+/// Left and right click cycle through the players, jump switches the camera mode.
+fn spectator_controls(
+    mut spectator: ResMut<Spectator>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    q_remote: Query<(&RemotePlayer, &Visibility)>,
+) {
+    let ids = targets(&q_remote);
+
+    // The followed player left or changed the level.
+    if spectator.target.is_some_and(|x| !ids.contains(&x)) {
+        spectator.target = ids.first().copied();
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        spectator.mode = spectator.mode.next();
+    }
+
+    let step = match (
+        mouse.just_pressed(MouseButton::Left),
+        mouse.just_pressed(MouseButton::Right),
+    ) {
+        (true, false) => 1,
+        (false, true) => ids.len().saturating_sub(1),
+        _ => 0,
+    };
+
+    if step == 0 || ids.is_empty() {
+        return;
+    }
+
+    let current = spectator
+        .target
+        .and_then(|x| ids.iter().position(|y| *y == x))
+        .unwrap_or(0);
+
+    spectator.target = Some(ids[(current + step) % ids.len()]);
+
+    if spectator.mode == SpectatorMode::Free {
+        spectator.mode = SpectatorMode::FirstPerson;
+    }
+}
+
+fn spectator_look(mut spectator: ResMut<Spectator>, look: Res<MouseLook>) {
+    spectator.yaw -= look.0.x;
+    spectator.pitch = (spectator.pitch - look.0.y).clamp(-FRAC_PI_2, FRAC_PI_2);
+}
+
+fn move_spectator_camera(
+    spectator: Res<Spectator>,
+    mut q_camera: Query<&mut Transform, With<SpectatorCamera>>,
+    q_remote: Query<(&RemotePlayer, &ReplicatedState)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+) {
+    let target = spectator
+        .target
+        .filter(|_| spectator.mode != SpectatorMode::Free)
+        .and_then(|id| q_remote.iter().find(|(x, _)| x.id == id))
+        .map(|(_, x)| x.0);
+
+    for mut transform in &mut q_camera {
+        let Some(state) = target else {
+            transform.rotation =
+                Quat::from_euler(EulerRot::YXZ, spectator.yaw, spectator.pitch, 0.);
+
+            let axis = |positive, negative| {
+                keys.pressed(positive) as i32 as f32 - keys.pressed(negative) as i32 as f32
+            };
+
+            let direction = transform.forward() * axis(KeyCode::KeyW, KeyCode::KeyS)
+                + transform.right() * axis(KeyCode::KeyD, KeyCode::KeyA)
+                + Vec3::Y * axis(KeyCode::KeyE, KeyCode::KeyQ);
+
+            let speed = match keys.pressed(KeyCode::ShiftLeft) {
+                true => FREE_SPEED_FAST,
+                false => FREE_SPEED,
+            };
+
+            transform.translation += direction.normalize_or_zero() * speed * time.delta_secs();
+            continue;
+        };
+
+        let eyes = state.translation() + Vec3::Y * EYE_HEIGHT;
+
+        match spectator.mode {
+            SpectatorMode::Chase => {
+                let behind = Quat::from_rotation_y(state.yaw) * Vec3::Z * CHASE_DISTANCE;
+
+                transform.translation = eyes + behind + Vec3::Y * CHASE_HEIGHT;
+                transform.look_at(eyes, Vec3::Y);
+            }
+            _ => {
+                transform.translation = eyes;
+                transform.rotation = Quat::from_euler(EulerRot::YXZ, state.yaw, state.pitch, 0.);
+            }
+        }
+    }
+}
+
+/// Speed and time of whoever is being watched, `None` with the free camera.
+pub fn spectated_state<'a>(
+    spectator: &Spectator,
+    q_remote: &'a Query<(&RemotePlayer, &ReplicatedState)>,
+) -> Option<(&'a str, PlayerState)> {
+    spectator
+        .target
+        .filter(|_| spectator.mode != SpectatorMode::Free)
+        .and_then(|id| q_remote.iter().find(|(x, _)| x.id == id))
+        .map(|(x, state)| (x.name.as_str(), state.0))
+}
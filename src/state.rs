@@ -14,6 +14,12 @@ impl Plugin for StatePlugin {
                     .run_if(in_state(PausedState::Running)),
             ),
         )
+        .configure_sets(
+            PreUpdate,
+            GameplaySet
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(PausedState::Running)),
+        )
         .configure_sets(
             FixedUpdate,
             (
@@ -28,7 +34,10 @@ impl Plugin for StatePlugin {
         )
         .insert_state(AppState::Loading)
         .init_state::<GameModeState>()
+        .init_state::<RaceState>()
+        .init_state::<ChatState>()
         .init_state::<PausedState>()
+        .init_state::<PracticeState>()
         .init_state::<DebugState>()
         .add_systems(OnExit(AppState::InGame), resume)
         .add_systems(OnEnter(PausedState::Paused), pause_physics)
@@ -66,6 +75,26 @@ pub enum GameModeState {
     Multiplayer,
 }
 
+/// Progress of the local player in a multiplayer race.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RaceState {
+    #[default]
+    None,
+    Countdown,
+    Racing,
+    Finished,
+    /// Joined after the start, waiting for the next race.
+    Watching,
+}
+
+/// Whether the chat is taking keyboard input.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChatState {
+    #[default]
+    Closed,
+    Open,
+}
+
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PausedState {
     Paused,
@@ -73,6 +102,14 @@ pub enum PausedState {
     Running,
 }
 
+/// Practice tools like rewinding only work while practicing, turning it on invalidates the run.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PracticeState {
+    #[default]
+    Off,
+    On,
+}
+
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DebugState {
     #[default]
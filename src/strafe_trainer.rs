@@ -0,0 +1,149 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_fps_controller::controller::*;
+
+use crate::prelude::*;
+
+pub const CL_STRAFETRAINER: &str = "cl_strafetrainer";
+
+/// Compares the air strafes of the local player with the angle that gains the most speed.
+pub struct StrafeTrainerPlugin;
+
+impl Plugin for StrafeTrainerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StrafeTrainer>()
+            .add_cvar(
+                CVar::bool(
+                    CL_STRAFETRAINER,
+                    false,
+                    "Show how close air strafes are to the optimal angle.",
+                )
+                .archived(),
+            )
+            .add_systems(PreUpdate, train_strafes.after(apply_mouse_look));
+    }
+}
+
+/// The last air strafe of the local player, `None` on the ground or without a strafe key.
+#[derive(Resource, Debug, Default)]
+pub struct StrafeTrainer(pub Option<StrafeAngle>);
+
+#[derive(Debug, Clone, Copy)]
+pub struct StrafeAngle {
+    /// Angle between the velocity and the wish direction in radians.
+    pub angle: f32,
+    /// The angle that gains the most speed this tick.
+    pub optimal: f32,
+    /// Speed gained relative to the optimal angle, between 0 and 1.
+    pub efficiency: f32,
+}
+
+/// Speed gained by air accelerating towards `angle`, like the controller does.
+fn air_gain(speed: f32, angle: f32, wish_speed: f32, acceleration: f32, dt: f32) -> f32 {
+    let add_speed = wish_speed - speed * angle.cos();
+
+    if add_speed <= 0. {
+        return 0.;
+    }
+
+    let accel_speed = (acceleration * wish_speed * dt).min(add_speed);
+
+    (speed * speed + accel_speed * accel_speed + 2. * speed * accel_speed * angle.cos()).sqrt()
+        - speed
+}
+
+/// The acceleration is fully used without overshooting the wish speed along the velocity.
+fn optimal_angle(speed: f32, wish_speed: f32, acceleration: f32, dt: f32) -> f32 {
+    let projected = wish_speed - acceleration * wish_speed * dt;
+
+    match speed > projected && speed > 0. {
+        true => (projected / speed).clamp(-1., 1.).acos(),
+        false => 0.,
+    }
+}
+
+/// Runs on the input of this frame, before the controller accelerates the player with it.
+fn train_strafes(
+    mut trainer: ResMut<StrafeTrainer>,
+    cvars: Res<CVars>,
+    time: Res<Time<Fixed>>,
+    q_player: Query<(&FpsController, &FpsControllerInput, &LinearVelocity), With<LogicalPlayer>>,
+) {
+    if !cvars.bool(CL_STRAFETRAINER) {
+        trainer.0 = None;
+        return;
+    }
+
+    let Ok((controller, input, velocity)) = q_player.single() else {
+        trainer.0 = None;
+        return;
+    };
+
+    let velocity = velocity.xz();
+    let wish = (Quat::from_rotation_y(input.yaw)
+        * Vec3::new(input.movement.x, 0., -input.movement.z))
+    .xz()
+    .normalize_or_zero();
+
+    if controller.ground_tick > 0 || velocity == Vec2::ZERO || wish == Vec2::ZERO {
+        trainer.0 = None;
+        return;
+    }
+
+    let speed = velocity.length();
+    let wish_speed = controller.walk_speed.min(controller.air_speed_cap);
+    // The controller accelerates once per tick.
+    let dt = time.timestep().as_secs_f32();
+
+    let angle = velocity.angle_to(wish).abs();
+    let optimal = optimal_angle(speed, wish_speed, controller.air_acceleration, dt);
+
+    let best = air_gain(speed, optimal, wish_speed, controller.air_acceleration, dt);
+    let gain = air_gain(speed, angle, wish_speed, controller.air_acceleration, dt);
+
+    trainer.0 = Some(StrafeAngle {
+        angle,
+        optimal,
+        efficiency: match best > 0. {
+            true => (gain / best).clamp(0., 1.),
+            false => 0.,
+        },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const WISH_SPEED: f32 = 1.;
+    const ACCELERATION: f32 = 100.;
+    const DT: f32 = 1. / 128.;
+
+    #[test]
+    fn optimal_angle_gains_the_most() {
+        let speed = 10.;
+        let optimal = optimal_angle(speed, WISH_SPEED, ACCELERATION, DT);
+        let gain = air_gain(speed, optimal, WISH_SPEED, ACCELERATION, DT);
+
+        assert!(gain > 0.);
+
+        for offset in [-0.1, -0.01, 0.01, 0.1] {
+            assert!(air_gain(speed, optimal + offset, WISH_SPEED, ACCELERATION, DT) <= gain + 1e-5);
+        }
+    }
+
+    #[test]
+    fn optimal_angle_at_low_speed() {
+        assert_eq!(optimal_angle(0., WISH_SPEED, ACCELERATION, DT), 0.);
+        // Below the wish speed the full acceleration fits straight ahead.
+        assert_eq!(optimal_angle(0.1, WISH_SPEED, ACCELERATION, DT), 0.);
+    }
+
+    #[test]
+    fn no_gain_past_the_wish_speed() {
+        assert_eq!(air_gain(10., 0., WISH_SPEED, ACCELERATION, DT), 0.);
+        assert!(air_gain(10., FRAC_PI_2, WISH_SPEED, ACCELERATION, DT) > 0.);
+    }
+}
@@ -0,0 +1,185 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_console::*;
+use bevy_fps_controller::controller::*;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Below this speed there is no direction to face away from.
+const BACKWARDS_MIN_SPEED: f32 = 1.;
+
+/// Selecting a style for the local player, the movement filter itself is part of the `MovementPlugin`.
+pub struct StylePlugin;
+
+impl Plugin for StylePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<StyleCommand, _>(style)
+            .add_systems(Update, sync_style.in_set(GameplaySet));
+    }
+}
+
+/// Restricts which movement keys count, runs are ranked per style.
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    ValueEnum,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum Style {
+    #[default]
+    Normal,
+    /// Only forward and back, facing sideways.
+    Sideways,
+    /// A forward key together with exactly one strafe key.
+    HalfSideways,
+    /// Only forward.
+    WOnly,
+    /// Facing away from the direction of travel.
+    Backwards,
+}
+
+impl std::fmt::Display for Style {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Style::Normal => write!(f, "Normal"),
+            Style::Sideways => write!(f, "Sideways"),
+            Style::HalfSideways => write!(f, "Half-Sideways"),
+            Style::WOnly => write!(f, "W-Only"),
+            Style::Backwards => write!(f, "Backwards"),
+        }
+    }
+}
+
+impl Style {
+    /// The movement of the controller input (x strafe, z forward) the style allows.
+    pub fn filter(&self, movement: Vec3, yaw: f32, velocity: Vec3) -> Vec3 {
+        match self {
+            Style::Normal => movement,
+            Style::Sideways => movement.with_x(0.),
+            Style::HalfSideways => match movement.z > 0. && movement.x != 0. {
+                true => movement,
+                false => movement.with_x(0.).with_z(0.),
+            },
+            Style::WOnly => movement.with_x(0.).with_z(movement.z.max(0.)),
+            Style::Backwards => {
+                let forward = (Quat::from_rotation_y(yaw) * Vec3::NEG_Z).xz();
+                let velocity = velocity.xz();
+
+                match velocity.length() > BACKWARDS_MIN_SPEED && forward.dot(velocity) > 0. {
+                    true => movement.with_x(0.).with_z(0.),
+                    false => movement,
+                }
+            }
+        }
+    }
+}
+
+/// Filters the input of every player by its style, before the controller moves it.
+pub fn apply_style(mut q_input: Query<(&Style, &mut FpsControllerInput, &LinearVelocity)>) {
+    for (style, mut input, velocity) in &mut q_input {
+        if *style == Style::Normal {
+            continue;
+        }
+
+        input.movement = style.filter(input.movement, input.yaw, velocity.0);
+    }
+}
+
+fn sync_style(category: Res<RunCategory>, mut q_style: Query<&mut Style, With<LogicalPlayer>>) {
+    for mut style in &mut q_style {
+        style.set_if_neq(category.style);
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "style")]
+struct StyleCommand {
+    style: Option<Style>,
+}
+
+fn style(
+    mut log: ConsoleCommand<StyleCommand>,
+    mut category: ResMut<RunCategory>,
+    app_state: Res<State<AppState>>,
+    mut ew: EventWriter<InvalidateRun>,
+) {
+    let Some(Ok(StyleCommand { style })) = log.take() else {
+        return;
+    };
+
+    let Some(style) = style else {
+        reply!(log, "style: {}", category.style);
+        return;
+    };
+
+    if category.style == style {
+        return;
+    }
+
+    category.style = style;
+
+    // The run so far was played with another style.
+    if *app_state.get() == AppState::InGame {
+        ew.write(InvalidateRun(Violation::CategoryChanged));
+    }
+
+    reply!(log, "style: {style}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    const FORWARD_RIGHT: Vec3 = Vec3::new(1., 0., 1.);
+
+    #[test]
+    fn filter_keys() {
+        let filter = |style: Style, movement| style.filter(movement, 0., Vec3::ZERO);
+
+        assert_eq!(filter(Style::Normal, FORWARD_RIGHT), FORWARD_RIGHT);
+        assert_eq!(filter(Style::Sideways, FORWARD_RIGHT), Vec3::Z);
+        assert_eq!(filter(Style::Sideways, Vec3::NEG_Z), Vec3::NEG_Z);
+        assert_eq!(filter(Style::HalfSideways, FORWARD_RIGHT), FORWARD_RIGHT);
+        assert_eq!(filter(Style::HalfSideways, Vec3::Z), Vec3::ZERO);
+        assert_eq!(filter(Style::HalfSideways, Vec3::X), Vec3::ZERO);
+        assert_eq!(filter(Style::WOnly, FORWARD_RIGHT), Vec3::Z);
+        assert_eq!(filter(Style::WOnly, Vec3::NEG_Z), Vec3::ZERO);
+    }
+
+    #[test]
+    fn filter_backwards() {
+        let speed = BACKWARDS_MIN_SPEED + 1.;
+
+        // Facing -z at a yaw of zero.
+        assert_eq!(
+            Style::Backwards.filter(FORWARD_RIGHT, 0., Vec3::NEG_Z * speed),
+            Vec3::ZERO
+        );
+        assert_eq!(
+            Style::Backwards.filter(FORWARD_RIGHT, 0., Vec3::Z * speed),
+            FORWARD_RIGHT
+        );
+        assert_eq!(
+            Style::Backwards.filter(FORWARD_RIGHT, PI, Vec3::NEG_Z * speed),
+            FORWARD_RIGHT
+        );
+        // Getting up to speed works in any direction.
+        assert_eq!(
+            Style::Backwards.filter(FORWARD_RIGHT, 0., Vec3::NEG_Z),
+            FORWARD_RIGHT
+        );
+    }
+}
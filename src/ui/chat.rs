@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::core::*;
+
+use super::*;
+
+/// Messages shown at once, older ones are only kept in the log.
+const VISIBLE_LINES: usize = 8;
+
+/// Seconds a message stays fully visible during gameplay before fading out.
+const SHOW_TIME: f32 = 8.;
+const FADE_TIME: f32 = 2.;
+
+pub struct ChatUiPlugin;
+
+impl Plugin for ChatUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), setup_chat)
+            .add_systems(OnExit(AppState::InGame), cleanup::<ChatUi>)
+            .add_systems(Update, update_chat.run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[derive(Component)]
+struct ChatUi;
+
+/// Lines are counted from the bottom, 0 is the newest message.
+#[derive(Component)]
+struct ChatLineText(usize);
+
+#[derive(Component)]
+struct ChatInputText;
+
+fn setup_chat(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: MARGIN * 2.,
+            bottom: Val::Percent(25.),
+            width: Val::Percent(40.),
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        Pickable::IGNORE,
+        ChatUi,
+    ))
+    .with_children(|parent| {
+        for i in (0..VISIBLE_LINES).rev() {
+            parent.spawn((
+                Text(String::new()),
+                ChatLineText(i),
+                text_resource.get_hud_text_props(18.0),
+            ));
+        }
+
+        parent.spawn((
+            Text(String::new()),
+            ChatInputText,
+            text_resource.get_text_props(18.0, Resurrect64::GOLD),
+            Visibility::Hidden,
+        ));
+    });
+}
+
+fn update_chat(
+    chat_log: Res<ChatLog>,
+    input: Res<ChatInput>,
+    state: Res<State<ChatState>>,
+    time: Res<Time<Real>>,
+    mut q_lines: Query<(&ChatLineText, &mut Text, &mut TextColor), Without<ChatInputText>>,
+    mut q_input: Query<(&mut Text, &mut Visibility), With<ChatInputText>>,
+) {
+    let open = *state.get() == ChatState::Open;
+    let now = time.elapsed();
+    let lines = chat_log
+        .lines()
+        .rev()
+        .take(VISIBLE_LINES)
+        .collect::<Vec<_>>();
+
+    for (ChatLineText(i), mut text, mut color) in &mut q_lines {
+        let Some(line) = lines.get(*i) else {
+            if !text.0.is_empty() {
+                text.0.clear();
+            }
+            continue;
+        };
+
+        let alpha = match open {
+            true => 1.,
+            false => {
+                let age = now.saturating_sub(line.received).as_secs_f32();
+                1. - ((age - SHOW_TIME) / FADE_TIME).clamp(0., 1.)
+            }
+        };
+
+        let base = match line.name {
+            Some(_) => HUD_TEXT_COLOR,
+            None => Resurrect64::PEACH,
+        };
+
+        let line = line.to_string();
+        if text.0 != line {
+            text.0 = line;
+        }
+
+        color.set_if_neq(TextColor(base.with_alpha(alpha)));
+    }
+
+    for (mut text, mut visibility) in &mut q_input {
+        visibility.set_if_neq(match open {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+
+        if open {
+            text.0 = format!("say: {}_", input.text);
+        }
+    }
+}
@@ -33,6 +33,9 @@ pub(super) struct LevelDurationText;
 #[derive(Component)]
 pub(super) struct RunDurationText;
 
+#[derive(Component)]
+pub(super) struct RunCategoryText;
+
 pub(super) fn get_header(
     text_resource: &Res<TextResource>,
 ) -> (impl Bundle, SpawnRelatedBundle<ChildOf, Spawn<impl Bundle>>) {
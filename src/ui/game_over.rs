@@ -14,20 +14,31 @@ struct GameOverMenu;
 
 impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::GameOver), setup)
-            .add_systems(
-                OnExit(AppState::GameOver),
-                (cleanup::<GameOverMenu>, cleanup::<Camera3d>),
-            );
+        app.add_systems(
+            OnEnter(AppState::GameOver),
+            setup.after(update_personal_bests),
+        )
+        .add_systems(
+            OnExit(AppState::GameOver),
+            (cleanup::<GameOverMenu>, cleanup::<Camera3d>),
+        );
     }
 }
 
-fn setup(mut cmd: Commands, text_resource: Res<TextResource>, run_duration: Res<RunDuration>) {
+fn setup(
+    mut cmd: Commands,
+    text_resource: Res<TextResource>,
+    run_duration: Res<RunDuration>,
+    integrity: Res<RunIntegrity>,
+    personal_bests: Res<PersonalBests>,
+    category: Res<RunCategory>,
+) {
     layout(&mut cmd).with_children(|cmd| {
         cmd.spawn(NodeBuilder::new().get_card())
             .with_children(|cmd| {
                 header(cmd, &text_resource);
                 content(cmd, &text_resource, &run_duration);
+                records(cmd, &text_resource, &integrity, &personal_bests, &category);
                 actions(cmd, &text_resource);
             });
     });
@@ -128,6 +139,48 @@ fn content(
     });
 }
 
+fn records(
+    cmd: &mut RelatedSpawnerCommands<'_, ChildOf>,
+    text_resource: &Res<TextResource>,
+    integrity: &Res<RunIntegrity>,
+    personal_bests: &Res<PersonalBests>,
+    category: &Res<RunCategory>,
+) {
+    let (text, color) = match integrity.badge() {
+        Some(badge) => (
+            format!(
+                "{badge} run ({}), not counted as personal best",
+                integrity
+                    .violations()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Resurrect64::SCARLET,
+        ),
+        None if personal_bests.improved => (
+            format!("New personal best ({})!", **category),
+            Resurrect64::GOLD,
+        ),
+        None => match personal_bests.get(category).and_then(|x| x.run) {
+            Some(run) => (
+                format!(
+                    "Personal best ({}): {}",
+                    **category,
+                    format_duration(run.as_secs_f32())
+                ),
+                Resurrect64::LIGHT_PURPLE,
+            ),
+            None => return,
+        },
+    };
+
+    cmd.spawn((
+        NodeBuilder::new().with_margin(UiRect::all(MARGIN)).get(),
+        children![(Text(text), text_resource.get_text_props(20.0, color))],
+    ));
+}
+
 fn handle_restart(_: Trigger<Pointer<Click>>, mut ns_app_state: ResMut<NextState<AppState>>) {
     ns_app_state.set(AppState::InGame);
 }
@@ -20,6 +20,8 @@ impl Plugin for HudPlugin {
                     update_speed_ui,
                     update_level_duration_ui,
                     update_run_duration_ui,
+                    update_run_category_ui,
+                    update_auto_jump_ui,
                 )
                     .in_set(GameplaySet),
             );
@@ -74,6 +76,22 @@ fn header(text_resource: &Res<TextResource>) -> impl Bundle {
                     text_resource.get_hud_text_props(19.),
                 )]
             ),
+            (
+                NodeBuilder::new().get_card(),
+                Pickable::IGNORE,
+                children![
+                    (
+                        Text(String::from("")),
+                        RunCategoryText,
+                        text_resource.get_hud_text_props(19.),
+                    ),
+                    (
+                        Text(String::from("")),
+                        AutoJumpUi,
+                        text_resource.get_text_props(16., Resurrect64::GOLD),
+                    )
+                ]
+            ),
         ],
     )
 }
@@ -132,6 +150,7 @@ fn update_level_duration_ui(
 fn update_run_duration_ui(
     run_duration: Res<RunDuration>,
     level_duration: Res<LevelDuration>,
+    integrity: Res<RunIntegrity>,
     mut text_query: Query<&mut Text, With<RunDurationText>>,
 ) {
     let stopwatch = level_duration.into_inner();
@@ -142,9 +161,41 @@ fn update_run_duration_ui(
         .sum::<f32>()
         + stopwatch.0.elapsed_secs();
 
-    let new_text = format!("Run: {}", format_duration(secs));
+    let new_text = match integrity.badge() {
+        Some(badge) => format!("Run: {} ({badge})", format_duration(secs)),
+        None => format!("Run: {}", format_duration(secs)),
+    };
 
     for mut text in &mut text_query {
         text.0 = new_text.clone();
     }
 }
+
+fn update_run_category_ui(
+    category: Res<RunCategory>,
+    mut text_query: Query<&mut Text, With<RunCategoryText>>,
+) {
+    let new_text = category.to_string();
+
+    for mut text in &mut text_query {
+        if text.0 != new_text {
+            text.0.clone_from(&new_text);
+        }
+    }
+}
+
+fn update_auto_jump_ui(
+    q_player: Query<Has<AutoJump>, With<LogicalPlayer>>,
+    mut text_query: Query<&mut Text, With<AutoJumpUi>>,
+) {
+    let new_text = match q_player.iter().any(|x| x) {
+        true => "Auto-Jump (practice)",
+        false => "",
+    };
+
+    for mut text in &mut text_query {
+        if text.0 != new_text {
+            text.0 = new_text.to_string();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::{console::*, core::*, input_display::*};
+
+use super::*;
+
+/// Radians per frame the mouse has to turn to light up its direction.
+const TURN_THRESHOLD: f32 = 0.0005;
+
+pub struct InputDisplayUiPlugin;
+
+impl Plugin for InputDisplayUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), setup_input_display)
+            .add_systems(OnExit(AppState::InGame), cleanup::<InputDisplayUi>)
+            .add_systems(
+                Update,
+                update_input_display.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct InputDisplayUi;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum KeyCard {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Jump,
+    Crouch,
+    TurnLeft,
+    TurnRight,
+}
+
+impl KeyCard {
+    fn label(&self) -> &'static str {
+        match self {
+            KeyCard::Forward => "W",
+            KeyCard::Back => "S",
+            KeyCard::Left => "A",
+            KeyCard::Right => "D",
+            KeyCard::Jump => "Jump",
+            KeyCard::Crouch => "Duck",
+            KeyCard::TurnLeft => "<",
+            KeyCard::TurnRight => ">",
+        }
+    }
+
+    fn pressed(&self, display: &InputDisplay) -> bool {
+        match self {
+            KeyCard::Forward => display.keys.forward,
+            KeyCard::Back => display.keys.back,
+            KeyCard::Left => display.keys.left,
+            KeyCard::Right => display.keys.right,
+            KeyCard::Jump => display.keys.jump.is_some(),
+            KeyCard::Crouch => display.keys.crouch,
+            KeyCard::TurnLeft => display.turn > TURN_THRESHOLD,
+            KeyCard::TurnRight => display.turn < -TURN_THRESHOLD,
+        }
+    }
+}
+
+fn key_card(key: KeyCard, text_resource: &TextResource) -> impl Bundle {
+    (
+        NodeBuilder::new()
+            .with_margin(UiRect::all(MARGIN / 4.))
+            .with_padding(UiRect::axes(PADDING, PADDING / 2.))
+            .get_card(),
+        Pickable::IGNORE,
+        key,
+        children![(
+            Text::new(key.label()),
+            text_resource.get_hud_text_props(16.0),
+        )],
+    )
+}
+
+fn key_row(keys: [KeyCard; 3], text_resource: &TextResource) -> impl Bundle {
+    (
+        NodeBuilder::new().with_direction(FlexDirection::Row).get(),
+        Pickable::IGNORE,
+        children![
+            key_card(keys[0], text_resource),
+            key_card(keys[1], text_resource),
+            key_card(keys[2], text_resource),
+        ],
+    )
+}
+
+fn setup_input_display(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            right: MARGIN * 2.,
+            bottom: MARGIN * 2.,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        Pickable::IGNORE,
+        Visibility::Hidden,
+        InputDisplayUi,
+        children![
+            key_row(
+                [KeyCard::TurnLeft, KeyCard::Forward, KeyCard::TurnRight],
+                &text_resource
+            ),
+            key_row(
+                [KeyCard::Left, KeyCard::Back, KeyCard::Right],
+                &text_resource
+            ),
+            (
+                NodeBuilder::new().with_direction(FlexDirection::Row).get(),
+                Pickable::IGNORE,
+                children![
+                    key_card(KeyCard::Jump, &text_resource),
+                    key_card(KeyCard::Crouch, &text_resource),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn update_input_display(
+    cvars: Res<CVars>,
+    display: Res<InputDisplay>,
+    mut q_ui: Query<&mut Visibility, With<InputDisplayUi>>,
+    mut q_cards: Query<(&KeyCard, &mut BackgroundColor, &Children)>,
+    mut q_text: Query<&mut Text>,
+) {
+    for mut visibility in &mut q_ui {
+        visibility.set_if_neq(match cvars.bool(CL_SHOWKEYS) {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+    }
+
+    for (key, mut color, children) in &mut q_cards {
+        color.set_if_neq(BackgroundColor(match key.pressed(&display) {
+            true => Resurrect64::GOLD.with_alpha(0.8),
+            false => NORMAL_BUTTON.with_alpha(0.7),
+        }));
+
+        if *key != KeyCard::Jump {
+            continue;
+        }
+
+        let label = match display.keys.jump {
+            Some(JumpKind::Scroll) => "Scroll",
+            Some(JumpKind::Auto) => "Auto",
+            _ => key.label(),
+        };
+
+        for child in children {
+            if let Ok(mut text) = q_text.get_mut(*child) {
+                if text.0 != label {
+                    text.0 = label.to_string();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{console::*, core::*, jumpstats::*};
+
+use super::*;
+
+pub struct JumpStatsUiPlugin;
+
+impl Plugin for JumpStatsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), setup_jump_stats)
+            .add_systems(OnExit(AppState::InGame), cleanup::<JumpStatsUi>)
+            .add_systems(Update, update_jump_stats.run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[derive(Component)]
+struct JumpStatsUi;
+
+#[derive(Component)]
+struct JumpStatsText;
+
+fn setup_jump_stats(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        NodeBuilder::new()
+            .with_grow(true)
+            .with_align_items(AlignItems::End)
+            .with_justify_content(JustifyContent::Center)
+            .with_margin(UiRect::right(MARGIN * 2.))
+            .get(),
+        Pickable::IGNORE,
+        Visibility::Hidden,
+        JumpStatsUi,
+        children![(
+            NodeBuilder::new().get_card(),
+            Pickable::IGNORE,
+            children![(
+                Text(String::from("No jumps yet.")),
+                JumpStatsText,
+                text_resource.get_hud_text_props(16.0),
+            )]
+        )],
+    ));
+}
+
+fn update_jump_stats(
+    cvars: Res<CVars>,
+    stats: Res<JumpStats>,
+    mut q_ui: Query<&mut Visibility, With<JumpStatsUi>>,
+    mut q_text: Query<&mut Text, With<JumpStatsText>>,
+) {
+    for mut visibility in &mut q_ui {
+        visibility.set_if_neq(match cvars.bool(CL_SHOWJUMPSTATS) {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+    }
+
+    if !stats.is_changed() {
+        return;
+    }
+
+    let Some(jump) = stats.last() else {
+        return;
+    };
+
+    for mut text in &mut q_text {
+        text.0 = jump.to_string();
+    }
+}
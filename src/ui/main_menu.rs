@@ -19,12 +19,21 @@ impl Plugin for MainMenuPlugin {
     }
 }
 
-fn setup(mut cmd: Commands, text_resource: Res<TextResource>) {
+#[derive(Component)]
+struct AutoBhopText;
+
+#[derive(Component)]
+struct PresetText;
+
+#[derive(Component)]
+struct TickRateText;
+
+fn setup(mut cmd: Commands, text_resource: Res<TextResource>, category: Res<RunCategory>) {
     layout(&mut cmd).with_children(|cmd| {
         cmd.spawn(NodeBuilder::new().get_card())
             .with_children(|cmd| {
                 header(cmd, &text_resource);
-                content(cmd, &text_resource);
+                content(cmd, &text_resource, &category);
             });
     });
 }
@@ -46,13 +55,47 @@ fn layout<'a>(cmd: &'a mut Commands) -> EntityCommands<'a> {
     ))
 }
 
-fn content(cmd: &mut RelatedSpawnerCommands<'_, ChildOf>, text_resource: &Res<TextResource>) {
+fn content(
+    cmd: &mut RelatedSpawnerCommands<'_, ChildOf>,
+    text_resource: &Res<TextResource>,
+    category: &RunCategory,
+) {
     cmd.spawn((
         NodeBuilder::new().get_button(),
         children![(Text::new("Play"), text_resource.get_button_text_props())],
     ))
     .observe(handle_play);
 
+    cmd.spawn((
+        NodeBuilder::new().get_button(),
+        children![(
+            Text(auto_bhop_label(category.auto_bhop)),
+            AutoBhopText,
+            text_resource.get_button_text_props()
+        )],
+    ))
+    .observe(toggle_auto_bhop);
+
+    cmd.spawn((
+        NodeBuilder::new().get_button(),
+        children![(
+            Text(preset_label(category.preset)),
+            PresetText,
+            text_resource.get_button_text_props()
+        )],
+    ))
+    .observe(cycle_preset);
+
+    cmd.spawn((
+        NodeBuilder::new().get_button(),
+        children![(
+            Text(tick_rate_label(category.tick_rate)),
+            TickRateText,
+            text_resource.get_button_text_props()
+        )],
+    ))
+    .observe(cycle_tick_rate);
+
     #[cfg(not(target_arch = "wasm32"))]
     cmd.spawn((
         NodeBuilder::new().get_button(),
@@ -63,6 +106,69 @@ fn content(cmd: &mut RelatedSpawnerCommands<'_, ChildOf>, text_resource: &Res<Te
     });
 }
 
-fn handle_play(_: Trigger<Pointer<Click>>, mut ns: ResMut<NextState<AppState>>) {
+fn handle_play(
+    _: Trigger<Pointer<Click>>,
+    mut ns: ResMut<NextState<AppState>>,
+    mut ns_mode: ResMut<NextState<GameModeState>>,
+) {
     ns.set(AppState::InGame);
+    ns_mode.set(GameModeState::Singleplayer);
+}
+
+fn auto_bhop_label(auto_bhop: bool) -> String {
+    match auto_bhop {
+        true => String::from("Auto-Bhop: On"),
+        false => String::from("Auto-Bhop: Off (Scroll)"),
+    }
+}
+
+/// The run category is chosen before playing, changing it mid-run invalidates the run.
+fn toggle_auto_bhop(
+    _: Trigger<Pointer<Click>>,
+    mut category: ResMut<RunCategory>,
+    mut q_text: Query<&mut Text, With<AutoBhopText>>,
+) {
+    category.auto_bhop = !category.auto_bhop;
+
+    for mut text in &mut q_text {
+        text.0 = auto_bhop_label(category.auto_bhop);
+    }
+}
+
+fn preset_label(preset: MovementPreset) -> String {
+    format!("Physics: {preset}")
+}
+
+fn cycle_preset(
+    _: Trigger<Pointer<Click>>,
+    mut category: ResMut<RunCategory>,
+    mut q_preset: Query<&mut Text, (With<PresetText>, Without<TickRateText>)>,
+    mut q_tick_rate: Query<&mut Text, With<TickRateText>>,
+) {
+    category.set_preset(category.preset.next());
+
+    for mut text in &mut q_preset {
+        text.0 = preset_label(category.preset);
+    }
+
+    // Presets come with their own tick rate.
+    for mut text in &mut q_tick_rate {
+        text.0 = tick_rate_label(category.tick_rate);
+    }
+}
+
+fn tick_rate_label(tick_rate: TickRate) -> String {
+    format!("Tickrate: {tick_rate}")
+}
+
+fn cycle_tick_rate(
+    _: Trigger<Pointer<Click>>,
+    mut category: ResMut<RunCategory>,
+    mut q_text: Query<&mut Text, With<TickRateText>>,
+) {
+    category.tick_rate = category.tick_rate.next();
+
+    for mut text in &mut q_text {
+        text.0 = tick_rate_label(category.tick_rate);
+    }
 }
@@ -1,10 +1,16 @@
+mod chat;
 mod core;
 mod game_over;
 mod hud;
+mod input_display;
+mod jumpstats;
 mod loading;
 mod main_menu;
 mod node_builder;
 mod pause;
+mod race;
+mod spectator;
+mod strafe_trainer;
 mod text_resource;
 
 pub use core::*;
@@ -14,12 +20,18 @@ use bevy_dev_tools::fps_overlay::*;
 use bevy_egui::EguiPlugin;
 use bevy_fps_controller::controller::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use chat::ChatUiPlugin;
 use game_over::GameOverPlugin;
 use hud::HudPlugin;
+use input_display::InputDisplayUiPlugin;
+use jumpstats::JumpStatsUiPlugin;
 use loading::LoadingScreenPlugin;
 use main_menu::MainMenuPlugin;
 use node_builder::*;
 use pause::PausePlugin;
+use race::RaceUiPlugin;
+use spectator::SpectatorHudPlugin;
+use strafe_trainer::StrafeTrainerUiPlugin;
 
 use crate::prelude::*;
 
@@ -52,6 +64,12 @@ impl Plugin for UiPlugin {
                 LoadingScreenPlugin,
                 PausePlugin,
                 HudPlugin,
+                RaceUiPlugin,
+                SpectatorHudPlugin,
+                ChatUiPlugin,
+                JumpStatsUiPlugin,
+                StrafeTrainerUiPlugin,
+                InputDisplayUiPlugin,
             ))
             .add_systems(Startup, setup_font)
             .add_systems(Update, button_system);
@@ -1,6 +1,6 @@
 use bevy::{ecs::relationship::RelatedSpawnerCommands, prelude::*};
 
-use crate::core::*;
+use crate::{console::*, core::*, mouse::*};
 
 use super::*;
 
@@ -8,14 +8,28 @@ pub struct PausePlugin;
 
 impl Plugin for PausePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(PausedState::Paused), setup_pause_menu)
-            .add_systems(OnExit(PausedState::Paused), cleanup::<PauseMenu>);
+        app.add_systems(
+            OnEnter(PausedState::Paused),
+            (setup_pause_menu, update_sensitivity_text).chain(),
+        )
+        .add_systems(OnExit(PausedState::Paused), cleanup::<PauseMenu>)
+        .add_systems(
+            Update,
+            update_sensitivity_text
+                .run_if(in_state(PausedState::Paused).and(resource_changed::<CVars>)),
+        );
     }
 }
 
 #[derive(Component)]
 struct PauseMenu;
 
+#[derive(Component)]
+struct SensitivityText;
+
+/// Sensitivity added or taken away by the buttons next to it.
+const SENSITIVITY_STEP: f32 = 0.1;
+
 fn setup_pause_menu(
     mut cmd: Commands,
     debug_state: Res<State<DebugState>>,
@@ -62,6 +76,31 @@ fn pause_menu_content(
     ))
     .observe(handle_resume);
 
+    cmd.spawn(NodeBuilder::new().with_direction(FlexDirection::Row).get())
+        .with_children(|cmd| {
+            cmd.spawn((
+                NodeBuilder::new().get_button(),
+                children![(Text::new("-"), text_resource.get_button_text_props())],
+            ))
+            .observe(|_: Trigger<Pointer<Click>>, cvars: ResMut<CVars>| {
+                step_sensitivity(cvars, -SENSITIVITY_STEP);
+            });
+
+            cmd.spawn((
+                Text::default(),
+                SensitivityText,
+                text_resource.get_button_text_props(),
+            ));
+
+            cmd.spawn((
+                NodeBuilder::new().get_button(),
+                children![(Text::new("+"), text_resource.get_button_text_props())],
+            ))
+            .observe(|_: Trigger<Pointer<Click>>, cvars: ResMut<CVars>| {
+                step_sensitivity(cvars, SENSITIVITY_STEP);
+            });
+        });
+
     cmd.spawn((
         NodeBuilder::new().get_button(),
         children![(
@@ -88,3 +127,23 @@ fn pause_menu_content(
 fn handle_resume(_: Trigger<Pointer<Click>>, mut ns: ResMut<NextState<PausedState>>) {
     ns.set(PausedState::Running);
 }
+
+fn step_sensitivity(mut cvars: ResMut<CVars>, step: f32) {
+    let sensitivity = ((cvars.float(SENSITIVITY) + step) * 100.).round() / 100.;
+
+    if let Err(e) = cvars.set(SENSITIVITY, &sensitivity.to_string()) {
+        warn!("{e}");
+    }
+}
+
+/// Shows the sensitivity together with the distance of a full turn, like a cm/360 calculator.
+fn update_sensitivity_text(cvars: Res<CVars>, mut q_text: Query<&mut Text, With<SensitivityText>>) {
+    for mut text in &mut q_text {
+        text.0 = format!(
+            "Sensitivity {} ({:.1} cm/360 at {} dpi)",
+            cvars.float(SENSITIVITY),
+            cm_per_360(&cvars),
+            cvars.float(M_DPI)
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::{ecs::relationship::RelatedSpawnerCommands, prelude::*};
+
+use crate::core::*;
+
+use super::*;
+
+/// How long "Go!" stays up after the countdown.
+const GO_TIME: f32 = 1.;
+
+pub struct RaceUiPlugin;
+
+impl Plugin for RaceUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(RaceState::Countdown), setup_countdown)
+            .add_systems(OnEnter(RaceState::Racing), start_countdown_fade)
+            .add_systems(OnEnter(RaceState::None), cleanup::<RaceCountdown>)
+            .add_systems(OnEnter(RaceState::Finished), setup_results)
+            .add_systems(OnExit(RaceState::Finished), cleanup::<RaceResults>)
+            .add_systems(
+                OnExit(AppState::InGame),
+                (cleanup::<RaceCountdown>, cleanup::<RaceResults>),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_countdown.run_if(in_state(RaceState::Countdown)),
+                    cleanup_timed::<RaceCountdown>,
+                    refresh_results.run_if(in_state(RaceState::Finished)),
+                ),
+            );
+    }
+}
+
+#[derive(Component)]
+struct RaceCountdown;
+
+#[derive(Component)]
+struct RaceCountdownText;
+
+#[derive(Component)]
+struct RaceResults;
+
+fn setup_countdown(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        NodeBuilder::new().with_grow(true).get(),
+        Pickable::IGNORE,
+        RaceCountdown,
+        children![(
+            Text(String::new()),
+            RaceCountdownText,
+            text_resource.get_text_props(96.0, Resurrect64::GOLD),
+        )],
+    ));
+}
+
+fn update_countdown(
+    status: Res<RaceStatus>,
+    time: Res<Time<Real>>,
+    mut q_text: Query<&mut Text, With<RaceCountdownText>>,
+) {
+    let text = format!("{:.0}", status.countdown(time.elapsed()).ceil());
+
+    for mut x in &mut q_text {
+        x.0.clone_from(&text);
+    }
+}
+
+fn start_countdown_fade(
+    mut cmd: Commands,
+    q_countdown: Query<Entity, With<RaceCountdown>>,
+    mut q_text: Query<&mut Text, With<RaceCountdownText>>,
+) {
+    for mut text in &mut q_text {
+        text.0 = String::from("Go!");
+    }
+
+    for entity in &q_countdown {
+        cmd.entity(entity).insert(Lifetime {
+            timer: Timer::from_seconds(GO_TIME, TimerMode::Once),
+        });
+    }
+}
+
+fn setup_results(
+    mut cmd: Commands,
+    text_resource: Res<TextResource>,
+    status: Res<RaceStatus>,
+    client: Option<Res<Client>>,
+    q_remote: Query<&RemotePlayer>,
+) {
+    let Some(info) = &status.info else {
+        return;
+    };
+
+    let local = client.and_then(|x| x.id());
+
+    cmd.spawn((
+        NodeBuilder::new().with_grow(true).get(),
+        RaceResults,
+        BackgroundColor(BACKGROUND.with_alpha(0.5)),
+    ))
+    .with_children(|cmd| {
+        cmd.spawn(NodeBuilder::new().get_card())
+            .with_children(|cmd| {
+                cmd.spawn(get_header(&text_resource));
+                results(cmd, &text_resource, info, local, &q_remote);
+                actions(cmd, &text_resource);
+            });
+    });
+}
+
+/// Rebuilds the results as more players finish, unless they were closed.
+fn refresh_results(
+    mut cmd: Commands,
+    status: Res<RaceStatus>,
+    q_results: Query<Entity, With<RaceResults>>,
+    text_resource: Res<TextResource>,
+    client: Option<Res<Client>>,
+    q_remote: Query<&RemotePlayer>,
+) {
+    if !status.is_changed() || q_results.is_empty() {
+        return;
+    }
+
+    for entity in &q_results {
+        cmd.entity(entity).despawn();
+    }
+
+    setup_results(cmd, text_resource, status, client, q_remote);
+}
+
+fn results(
+    cmd: &mut RelatedSpawnerCommands<'_, ChildOf>,
+    text_resource: &Res<TextResource>,
+    info: &RaceInfo,
+    local: Option<PlayerId>,
+    q_remote: &Query<&RemotePlayer>,
+) {
+    let winner = info.finished.first().map_or(0., |x| x.time);
+
+    cmd.spawn((
+        NodeBuilder::new().get_card(),
+        children![(
+            NodeBuilder::new().with_margin(UiRect::all(MARGIN)).get(),
+            children![(
+                Text(format!("Level {} results", info.level)),
+                text_resource.get_text_props(32.0, Resurrect64::BRIGHT_GREEN),
+            )],
+        )],
+    ))
+    .with_children(|cmd| {
+        for (i, x) in info.finished.iter().enumerate() {
+            let gap = match i {
+                0 => String::new(),
+                _ => format!(" +{:.3}s", x.time - winner),
+            };
+
+            cmd.spawn((
+                NodeBuilder::new().get(),
+                children![(
+                    Text(format!("#{} {} {:.3}s{gap}", i + 1, x.name, x.time)),
+                    text_resource.get_text_props(
+                        24.0,
+                        match Some(x.id) == local {
+                            true => Resurrect64::GOLD,
+                            false => Resurrect64::LIGHT_PURPLE,
+                        }
+                    ),
+                )],
+            ));
+        }
+
+        for x in q_remote
+            .iter()
+            .filter(|x| !info.finished.iter().any(|y| y.id == x.id))
+        {
+            cmd.spawn((
+                NodeBuilder::new().get(),
+                children![(
+                    Text(format!("{} still racing", x.name)),
+                    text_resource.get_text_props(20.0, Resurrect64::GRAY_PURPLE_1),
+                )],
+            ));
+        }
+    });
+}
+
+fn actions(cmd: &mut RelatedSpawnerCommands<'_, ChildOf>, text_resource: &Res<TextResource>) {
+    cmd.spawn((NodeBuilder::new().with_direction(FlexDirection::Row).get(),))
+        .with_children(|cmd| {
+            cmd.spawn((
+                NodeBuilder::new().get_button(),
+                children![(
+                    Text::new("Keep Playing"),
+                    text_resource.get_button_text_props()
+                )],
+            ))
+            .observe(
+                |_: Trigger<Pointer<Click>>,
+                 mut cmd: Commands,
+                 q_results: Query<Entity, With<RaceResults>>| {
+                    for entity in &q_results {
+                        cmd.entity(entity).despawn();
+                    }
+                },
+            );
+
+            cmd.spawn((
+                NodeBuilder::new().get_button(),
+                children![(Text::new("Spectate"), text_resource.get_button_text_props())],
+            ))
+            .observe(
+                |_: Trigger<Pointer<Click>>,
+                 mut cmd: Commands,
+                 q_results: Query<Entity, With<RaceResults>>,
+                 mut ns: ResMut<NextState<SpectatorState>>| {
+                    for entity in &q_results {
+                        cmd.entity(entity).despawn();
+                    }
+                    ns.set(SpectatorState::Spectating);
+                },
+            );
+
+            cmd.spawn((
+                NodeBuilder::new().get_button(),
+                children![(
+                    Text::new("Disconnect"),
+                    text_resource.get_button_text_props()
+                )],
+            ))
+            .observe(
+                |_: Trigger<Pointer<Click>>, mut ew: EventWriter<ClientDisconnected>| {
+                    ew.write(ClientDisconnected(String::from("Left after the race.")));
+                },
+            );
+        });
+}
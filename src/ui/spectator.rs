@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::core::*;
+
+use super::*;
+
+pub struct SpectatorHudPlugin;
+
+impl Plugin for SpectatorHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SpectatorState::Spectating), setup_spectator_hud)
+            .add_systems(OnExit(SpectatorState::Spectating), cleanup::<SpectatorHud>)
+            .add_systems(
+                Update,
+                update_spectator_hud.run_if(in_state(SpectatorState::Spectating)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SpectatorHud;
+
+#[derive(Component)]
+struct SpectatorHudText;
+
+fn setup_spectator_hud(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        NodeBuilder::new()
+            .with_grow(true)
+            .with_align_items(AlignItems::Center)
+            .with_justify_content(JustifyContent::End)
+            .with_margin(UiRect::bottom(MARGIN * 12.))
+            .get(),
+        Pickable::IGNORE,
+        SpectatorHud,
+        children![(
+            NodeBuilder::new().get_card(),
+            Pickable::IGNORE,
+            children![(
+                Text(String::new()),
+                SpectatorHudText,
+                text_resource.get_hud_text_props(19.0),
+            )]
+        )],
+    ));
+}
+
+fn update_spectator_hud(
+    spectator: Res<Spectator>,
+    q_remote: Query<(&RemotePlayer, &ReplicatedState)>,
+    mut q_text: Query<&mut Text, With<SpectatorHudText>>,
+) {
+    let text = match spectated_state(&spectator, &q_remote) {
+        Some((name, state)) => format!(
+            "Spectating {name} ({})\nSpeed: {:.2}  Level {}: {}",
+            spectator.mode,
+            state.velocity().xz().length(),
+            state.level,
+            format_duration(state.level_time)
+        ),
+        None => format!("{} camera", SpectatorMode::Free),
+    };
+
+    for mut x in &mut q_text {
+        x.0.clone_from(&text);
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{core::*, strafe_trainer::*};
+
+use super::*;
+
+/// Degrees off the optimal angle at either end of the bar.
+const BAR_RANGE: f32 = 20.;
+const BAR_WIDTH: f32 = 320.;
+const MARKER_WIDTH: f32 = 6.;
+
+pub struct StrafeTrainerUiPlugin;
+
+impl Plugin for StrafeTrainerUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), setup_strafe_trainer)
+            .add_systems(OnExit(AppState::InGame), cleanup::<StrafeTrainerUi>)
+            .add_systems(
+                Update,
+                update_strafe_trainer.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct StrafeTrainerUi;
+
+#[derive(Component)]
+struct StrafeMarker;
+
+#[derive(Component)]
+struct StrafeEfficiencyText;
+
+fn setup_strafe_trainer(mut cmd: Commands, text_resource: Res<TextResource>) {
+    cmd.spawn((
+        NodeBuilder::new()
+            .with_grow(true)
+            .with_justify_content(JustifyContent::End)
+            .with_margin(UiRect::bottom(MARGIN * 20.))
+            .get(),
+        Pickable::IGNORE,
+        Visibility::Hidden,
+        StrafeTrainerUi,
+        children![
+            (
+                Node {
+                    width: Val::Px(BAR_WIDTH),
+                    height: PADDING,
+                    ..default()
+                },
+                BackgroundColor(Resurrect64::DARK_PURPLE_2.with_alpha(0.8)),
+                BorderRadius::all(BORDER_RADIUS),
+                Pickable::IGNORE,
+                children![
+                    // The optimal angle.
+                    (
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(50.),
+                            width: Val::Px(2.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(HUD_TEXT_COLOR),
+                        Pickable::IGNORE,
+                    ),
+                    (
+                        Node {
+                            position_type: PositionType::Absolute,
+                            width: Val::Px(MARKER_WIDTH),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(Resurrect64::SCARLET),
+                        BorderRadius::all(BORDER_RADIUS),
+                        Pickable::IGNORE,
+                        StrafeMarker,
+                    )
+                ],
+            ),
+            (
+                Text(String::new()),
+                StrafeEfficiencyText,
+                text_resource.get_hud_text_props(16.0),
+            )
+        ],
+    ));
+}
+
+fn efficiency_color(efficiency: f32) -> Color {
+    match efficiency {
+        x if x >= 0.9 => Resurrect64::BRIGHT_GREEN,
+        x if x >= 0.6 => Resurrect64::GOLD,
+        _ => Resurrect64::SCARLET,
+    }
+}
+
+/// Left of the center turns too little, right of it too much.
+fn update_strafe_trainer(
+    trainer: Res<StrafeTrainer>,
+    mut q_ui: Query<&mut Visibility, With<StrafeTrainerUi>>,
+    mut q_marker: Query<(&mut Node, &mut BackgroundColor), With<StrafeMarker>>,
+    mut q_text: Query<&mut Text, With<StrafeEfficiencyText>>,
+) {
+    for mut visibility in &mut q_ui {
+        visibility.set_if_neq(match trainer.0 {
+            Some(_) => Visibility::Inherited,
+            None => Visibility::Hidden,
+        });
+    }
+
+    let Some(strafe) = trainer.0 else {
+        return;
+    };
+
+    let offset = ((strafe.angle - strafe.optimal).to_degrees() / BAR_RANGE).clamp(-1., 1.);
+
+    for (mut node, mut color) in &mut q_marker {
+        node.left = Val::Px((offset + 1.) / 2. * BAR_WIDTH - MARKER_WIDTH / 2.);
+        color.set_if_neq(BackgroundColor(efficiency_color(strafe.efficiency)));
+    }
+
+    for mut text in &mut q_text {
+        text.0 = format!("{:.0}%", strafe.efficiency * 100.);
+    }
+}
@@ -1,10 +1,6 @@
-use bevy::{
-    audio::{PlaybackMode, Volume},
-    prelude::*,
-};
+use bevy::prelude::*;
 
 use avian3d::prelude::*;
-use bevy_hanabi::ParticleEffect;
 use std::num::NonZeroUsize;
 
 use super::*;
@@ -24,6 +20,15 @@ impl Plugin for ColliderPlugin {
             )
                 .after(spawn_world)
                 .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            Update,
+            (
+                record_checkpoints,
+                // Races end on the level they are run on, see `RacePlugin`.
+                advance_level.run_if(in_state(RaceState::None)),
+            )
+                .run_if(in_state(AppState::InGame)),
         );
     }
 }
@@ -90,32 +95,24 @@ fn checkpoint_colliders(
 
 fn checkpoint_collision(
     trigger: Trigger<OnCollisionStart>,
-    mut cmd: Commands,
-    mut history: ResMut<History>,
-    current_lvl: Res<CurrentLevel>,
-    fx: Res<ParticleEffects>,
-    sounds: Res<Sounds>,
+    mut ew: EventWriter<CheckpointReached>,
 ) {
-    history.0.push(trigger.target());
-
-    let other_entity = trigger.collider;
+    ew.write(CheckpointReached {
+        checkpoint: trigger.target(),
+        entity: trigger.collider,
+    });
+}
 
-    cmd.entity(other_entity).with_child((
-        ParticleEffect::new(fx.get_checkpoint_fx(current_lvl.get())),
-        Visibility::Visible,
-        Lifetime {
-            timer: Timer::from_seconds(2., TimerMode::Once),
-        },
-    ));
-
-    cmd.spawn((
-        AudioPlayer::new(sounds.glass_sound.clone()),
-        PlaybackSettings {
-            mode: PlaybackMode::Despawn,
-            volume: Volume::Linear(0.1),
-            ..default()
-        },
-    ));
+fn record_checkpoints(
+    mut er: EventReader<CheckpointReached>,
+    mut history: ResMut<History>,
+    q_player: Query<(), With<LogicalPlayer>>,
+) {
+    for e in er.read() {
+        if q_player.contains(e.entity) {
+            history.0.push(e.checkpoint);
+        }
+    }
 }
 
 fn end_colliders(
@@ -139,14 +136,25 @@ fn end_colliders(
     }
 }
 
-fn end_collision(
-    _: Trigger<OnCollisionStart>,
+fn end_collision(trigger: Trigger<OnCollisionStart>, mut ew: EventWriter<LevelFinished>) {
+    ew.write(LevelFinished {
+        entity: trigger.collider,
+    });
+}
+
+fn advance_level(
+    mut er: EventReader<LevelFinished>,
+    q_player: Query<(), With<LogicalPlayer>>,
     current_lvl: Res<CurrentLevel>,
     mut ns: ResMut<NextState<AppState>>,
     mut ew: EventWriter<SpawnLevel>,
     level_duration: Res<LevelDuration>,
     mut run_duration: ResMut<RunDuration>,
 ) {
+    if !er.read().any(|e| q_player.contains(e.entity)) {
+        return;
+    }
+
     let next_level = current_lvl.get().get() + 1;
 
     run_duration.results[current_lvl.get().get() - 1] = level_duration.0.elapsed();
@@ -162,18 +170,13 @@ fn end_collision(
 fn boost_colliders(
     mut cmd: Commands,
     main_scene: Res<MainScene>,
-    q_boost: Query<(Entity, &MeshMaterial3d<StandardMaterial>), (With<SpeedBoost>, Without<Ready>)>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    effects: Res<ParticleEffects>,
+    q_boost: Query<Entity, (With<SpeedBoost>, Without<Ready>)>,
 ) {
     if !main_scene.is_spawned {
         return;
     }
 
-    for (boost, mat) in &q_boost {
-        let material = materials.get_mut(mat).unwrap();
-        material.unlit = true;
-
+    for boost in &q_boost {
         cmd.entity(boost)
             .insert((
                 Ready,
@@ -183,16 +186,6 @@ fn boost_colliders(
                 ),
                 ColliderConstructor::ConvexHullFromMesh,
                 CollisionEventsEnabled,
-                children![
-                    ParticleEffect::new(effects.boost_idle_fx.clone()),
-                    PointLight {
-                        color: Resurrect64::GREEN,
-                        radius: 3.0,
-                        intensity: 3_000_000.0,
-                        shadows_enabled: false,
-                        ..default()
-                    }
-                ],
             ))
             .observe(boost_collision);
     }
@@ -200,14 +193,9 @@ fn boost_colliders(
 
 fn boost_collision(
     trigger: Trigger<OnCollisionEnd>,
-    mut cmd: Commands,
-    q_gtf: Query<&GlobalTransform>,
-    fx: Res<ParticleEffects>,
     mut q_boosted: Query<&mut LinearVelocity>,
-    sounds: Res<Sounds>,
+    mut ew: EventWriter<Boosted>,
 ) {
-    let boost = trigger.target();
-
     let other_entity = trigger.collider;
 
     let Ok(mut boosted) = q_boosted.get_mut(other_entity) else {
@@ -218,33 +206,8 @@ fn boost_collision(
 
     boosted.0 *= Vec3::splat(boost_value).with_y(1.);
 
-    let Ok(gtf) = q_gtf.get(boost) else {
-        return;
-    };
-
-    cmd.entity(other_entity).with_child((
-        ParticleEffect::new(fx.player_boost_fx.clone()),
-        Visibility::Visible,
-        Lifetime {
-            timer: Timer::from_seconds(2., TimerMode::Once),
-        },
-    ));
-
-    cmd.spawn((
-        Visibility::Visible,
-        ParticleEffect::new(fx.boost_fx.clone()),
-        Transform::from_translation(gtf.translation()),
-        Lifetime {
-            timer: Timer::from_seconds(2., TimerMode::Once),
-        },
-    ));
-
-    cmd.spawn((
-        AudioPlayer::new(sounds.boost_sound.clone()),
-        PlaybackSettings {
-            mode: PlaybackMode::Despawn,
-            volume: Volume::Linear(0.2),
-            ..default()
-        },
-    ));
+    ew.write(Boosted {
+        boost: trigger.target(),
+        entity: other_entity,
+    });
 }
@@ -0,0 +1,152 @@
+use std::f32::consts::TAU;
+
+use bevy::{core_pipeline::Skybox, prelude::*};
+use bevy_hanabi::ParticleEffect;
+use bevy_water::*;
+
+use super::*;
+
+const WATER_HEIGHT: f32 = 10.0;
+
+/// Lighting, skyboxes, water and decorations of the levels, only needed where they are rendered.
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WaterSettings {
+            height: WATER_HEIGHT,
+            ..default()
+        })
+        .add_plugins(WaterPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(
+            FixedUpdate,
+            (setup_water, translate_water)
+                .after(spawn_world)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            Update,
+            (apply_level_environment, decorate_boosts).run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Update, rotate_speed_boost.in_set(GameplaySet))
+        .insert_resource(AmbientLight {
+            color: Color::WHITE,
+            brightness: 10000.0,
+            affects_lightmapped_meshes: true,
+        });
+    }
+}
+
+#[derive(Resource)]
+struct Skyboxes([Handle<Image>; LEVEL_COUNT]);
+
+impl Skyboxes {
+    fn get(&self, level: NonZeroUsize) -> &Handle<Image> {
+        &self.0[level.get() - 1]
+    }
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, mut loading: ResMut<AssetsLoading>) {
+    commands.spawn((
+        DirectionalLight {
+            illuminance: light_consts::lux::DIRECT_SUNLIGHT,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 7.0, -4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    let skyboxes: [Handle<Image>; LEVEL_COUNT] = (1..=LEVEL_COUNT)
+        .map(|x| assets.load(format!("skybox/skybox_{:?}_skybox.ktx2", x)) as Handle<Image>)
+        .collect::<Vec<Handle<Image>>>()
+        .try_into()
+        .unwrap();
+
+    skyboxes
+        .iter()
+        .map(|x| x.clone().into())
+        .for_each(|x| loading.0.push(x));
+
+    commands.insert_resource(Skyboxes(skyboxes));
+}
+
+fn setup_water(mut q_water: Query<&mut Transform, (With<WaterTiles>, Without<Ready>)>) {
+    for mut water in &mut q_water {
+        water.scale = Vec3::splat(8.);
+    }
+}
+
+fn translate_water(
+    mut q_water: Query<&mut Transform, With<WaterTiles>>,
+    history: Res<History>,
+    q_gtf: Query<&GlobalTransform, With<CheckPoint>>,
+) {
+    let spawn_point = history.last(q_gtf);
+    for mut water in &mut q_water {
+        water.translation.y = spawn_point.y - 170.;
+    }
+}
+
+fn apply_level_environment(
+    mut cmd: Commands,
+    mut er: EventReader<LevelSpawned>,
+    skyboxes: Res<Skyboxes>,
+    q_camera: Query<Entity, With<Camera3d>>,
+    mut water_settings: ResMut<WaterSettings>,
+) {
+    for LevelSpawned(level) in er.read() {
+        for entity in &q_camera {
+            cmd.entity(entity).remove::<Skybox>().insert(Skybox {
+                image: skyboxes.get(*level).clone(),
+                brightness: match level.get() {
+                    1 => 30000.,
+                    2 => 50000.,
+                    3 => 50000.,
+                    _ => 10000.,
+                },
+                ..default()
+            });
+        }
+
+        water_settings.deep_color = match level.get() {
+            1 => Resurrect64::DEEP_PURPLE,
+            2 => Resurrect64::DARK_CYAN,
+            3 => Resurrect64::DARK_RED_1,
+            _ => Resurrect64::DARK_CYAN,
+        };
+    }
+}
+
+fn decorate_boosts(
+    mut cmd: Commands,
+    q_boost: Query<(Entity, &MeshMaterial3d<StandardMaterial>), (With<SpeedBoost>, Added<Ready>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    effects: Res<ParticleEffects>,
+) {
+    for (boost, mat) in &q_boost {
+        if let Some(material) = materials.get_mut(mat) {
+            material.unlit = true;
+        }
+
+        cmd.entity(boost).insert(children![
+            ParticleEffect::new(effects.boost_idle_fx.clone()),
+            PointLight {
+                color: Resurrect64::GREEN,
+                radius: 3.0,
+                intensity: 3_000_000.0,
+                shadows_enabled: false,
+                ..default()
+            }
+        ]);
+    }
+}
+
+fn rotate_speed_boost(mut cubes: Query<&mut Transform, With<SpeedBoost>>, timer: Res<Time>) {
+    for mut transform in &mut cubes {
+        let rotation = TAU * timer.delta_secs();
+        transform.rotate_x(0.1 * rotation);
+        transform.rotate_z(0.1 * rotation);
+        transform.rotate_y(0.5 * rotation);
+    }
+}